@@ -0,0 +1,163 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::{
+    class::LoxInstance,
+    environment::Environment,
+    interpreter::{ErrorKind, Interpreter, RuntimeError},
+    stmt::FunctionDecl,
+    value::Value,
+};
+
+/// A value which can be invoked with a call expression.
+pub trait Callable: fmt::Debug + fmt::Display {
+    /// The name shown for calls to this callable in stack traces.
+    fn name(&self) -> &str;
+
+    /// The number of arguments the callable expects.
+    fn arity(&self) -> usize;
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError>;
+}
+
+/// A user-defined function along with the environment it was declared in.
+pub struct LoxFunction {
+    declaration: Rc<FunctionDecl>,
+    closure: Rc<RefCell<Environment>>,
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(
+        declaration: Rc<FunctionDecl>,
+        closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    ) -> Self {
+        Self {
+            declaration,
+            closure,
+            is_initializer,
+        }
+    }
+
+    /// Returns a copy of this method with `this` bound to `instance`.
+    pub fn bind(&self, instance: Rc<RefCell<LoxInstance>>) -> LoxFunction {
+        let mut environment = Environment::new_enclosed(Rc::clone(&self.closure));
+        environment.define(String::from("this"), Value::Instance(instance));
+        LoxFunction::new(
+            Rc::clone(&self.declaration),
+            Rc::new(RefCell::new(environment)),
+            self.is_initializer,
+        )
+    }
+
+    /// The instance bound to `this` in an initializer's closure.
+    fn this(&self) -> Value {
+        self.closure
+            .borrow()
+            .get_at(0, "this")
+            .expect("initializers are bound to an instance")
+    }
+}
+
+impl Callable for LoxFunction {
+    fn name(&self) -> &str {
+        &self.declaration.name.lexeme
+    }
+
+    fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        let mut environment = Environment::new_enclosed(Rc::clone(&self.closure));
+        for (param, argument) in self.declaration.params.iter().zip(arguments) {
+            environment.define(param.lexeme.clone(), argument);
+        }
+        let result = match interpreter.execute_block(&self.declaration.body, environment) {
+            Ok(()) => Value::Nil,
+            Err(err) => match *err.kind {
+                ErrorKind::Return(value) => value,
+                _ => return Err(err),
+            },
+        };
+        // Initializers always return `this`, even on an early `return;`.
+        if self.is_initializer {
+            Ok(self.this())
+        } else {
+            Ok(result)
+        }
+    }
+}
+
+// The closure may (indirectly) contain this function, so only the name is
+// printed to avoid infinite recursion.
+impl fmt::Debug for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "LoxFunction({})", self.declaration.name.lexeme)
+    }
+}
+
+impl fmt::Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.declaration.name.lexeme)
+    }
+}
+
+/// A function implemented in Rust and exposed to Lox code.
+pub struct NativeFunction {
+    name: &'static str,
+    arity: usize,
+    function: NativeFn,
+}
+
+/// The implementation of a native function. Errors may be raised at the call
+/// site with [`Interpreter::native_error`].
+pub type NativeFn = fn(&Interpreter, Vec<Value>) -> Result<Value, RuntimeError>;
+
+impl NativeFunction {
+    pub fn new(name: &'static str, arity: usize, function: NativeFn) -> Self {
+        Self {
+            name,
+            arity,
+            function,
+        }
+    }
+}
+
+impl Callable for NativeFunction {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        (self.function)(interpreter, arguments)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NativeFunction({})", self.name)
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
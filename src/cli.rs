@@ -0,0 +1,146 @@
+use crate::{
+    interpreter::{self, script_name, Interpreter, LoxError, STDIN},
+    parser::Parser,
+    repl::{self, Repl},
+    scanner::Scanner,
+};
+
+pub const USAGE: &str = "\
+Usage: rlox [command]
+
+Commands:
+  run <file> [args...]  Run a script with the given arguments
+  repl                  Start an interactive session (the default)
+  tokens <file>         Print the tokens of a script
+  ast <file>            Print the syntax tree of a script
+  check <file>          Report errors in a script without running it
+  -e <code>             Run <code>
+  <file> [args...]      Same as `run <file> [args...]`
+  -h, --help            Print this message
+
+A <file> of `-` reads the script from stdin. Scripts can read their arguments
+from the global list `args`.";
+
+/// What the binary was asked to do.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// Run a script with the given arguments.
+    Run(String, Vec<String>),
+    Repl,
+    Tokens(String),
+    Ast(String),
+    Check(String),
+    Eval(String),
+    Help,
+}
+
+impl Command {
+    /// Parses the command-line arguments, not including the program name.
+    pub fn parse(args: &[String]) -> Result<Command, String> {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        match args.as_slice() {
+            [] | ["repl"] => Ok(Command::Repl),
+            ["-h" | "--help" | "help"] => Ok(Command::Help),
+            ["run", file, args @ ..] => Ok(Command::run(file, args)),
+            ["tokens", file] => Ok(Command::Tokens(file.to_string())),
+            ["ast", file] => Ok(Command::Ast(file.to_string())),
+            ["check", file] => Ok(Command::Check(file.to_string())),
+            ["-e", code] => Ok(Command::Eval(code.to_string())),
+            ["run"] => Err(String::from("'run' expects a file")),
+            [name @ ("tokens" | "ast" | "check"), ..] => {
+                Err(format!("'{}' expects a single file", name))
+            }
+            ["-e", ..] => Err(String::from("'-e' expects a single argument")),
+            [option, ..] if option.starts_with('-') && *option != STDIN => {
+                Err(format!("unrecognized option '{}'", option))
+            }
+            [file, args @ ..] => Ok(Command::run(file, args)),
+        }
+    }
+
+    fn run(file: &str, args: &[&str]) -> Command {
+        let args = args.iter().map(|arg| arg.to_string()).collect();
+        Command::Run(file.to_string(), args)
+    }
+
+    pub fn execute(self) -> Result<(), LoxError> {
+        match self {
+            Command::Run(file, args) => Interpreter::new().with_args(args).run_file(&file),
+            Command::Repl => Repl::new(Interpreter::new())?.run(),
+            Command::Tokens(file) => {
+                let source = interpreter::read_script(&file)?;
+                let (tokens, errors) = repl::list_tokens(&source);
+                print!("{}", tokens);
+                if errors.is_empty() {
+                    return Ok(());
+                }
+                let err = LoxError::from(errors);
+                interpreter::report(&err, &source, script_name(&file));
+                Err(err)
+            }
+            Command::Ast(file) => {
+                let source = interpreter::read_script(&file)?;
+                let (tokens, errors) = Scanner::new(&source).scan_tokens();
+                let stmts = Parser::new(tokens, errors)
+                    .parse()
+                    .map_err(LoxError::from)
+                    .inspect_err(|err| interpreter::report(err, &source, script_name(&file)))?;
+                for stmt in stmts {
+                    println!("{}", stmt);
+                }
+                Ok(())
+            }
+            Command::Check(file) => {
+                let source = interpreter::read_script(&file)?;
+                Interpreter::new()
+                    .compile(&source, script_name(&file))
+                    .map_err(LoxError::from)
+                    .inspect_err(|err| interpreter::report(err, &source, script_name(&file)))?;
+                Ok(())
+            }
+            Command::Eval(code) => Interpreter::new().run_source(&code, "<eval>"),
+            Command::Help => {
+                println!("{}", USAGE);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        Command::parse(&args)
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(Ok(Command::Repl), parse(&[]));
+        assert_eq!(Ok(Command::Run("a.lox".into(), vec![])), parse(&["a.lox"]));
+        assert_eq!(
+            Ok(Command::Run("a.lox".into(), vec![])),
+            parse(&["run", "a.lox"])
+        );
+        assert_eq!(
+            Ok(Command::Run("a.lox".into(), vec!["-v".into(), "b".into()])),
+            parse(&["a.lox", "-v", "b"])
+        );
+        assert_eq!(Ok(Command::Ast("a.lox".into())), parse(&["ast", "a.lox"]));
+        assert_eq!(
+            Ok(Command::Eval("print 1;".into())),
+            parse(&["-e", "print 1;"])
+        );
+        assert!(parse(&["check"]).is_err());
+        assert!(parse(&["--verbose"]).is_err());
+        assert!(parse(&["run"]).is_err());
+        assert_eq!(Ok(Command::Run("-".into(), vec![])), parse(&["-"]));
+        assert_eq!(
+            Ok(Command::Run("-".into(), vec!["a".into()])),
+            parse(&["run", "-", "a"])
+        );
+        assert_eq!(Ok(Command::Check("-".into())), parse(&["check", "-"]));
+    }
+}
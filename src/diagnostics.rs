@@ -0,0 +1,256 @@
+use std::fmt::Write;
+
+use crate::{
+    interpreter::{ErrorKind, LoxError, RuntimeError, TraceFrame},
+    parser::{CompileErrors, ParseError},
+    scanner::{ScannerError, ScannerErrorKind},
+    token::Span,
+};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// The number of frames shown from each end of a long stack trace.
+const TRACE_ENDS: usize = 10;
+
+/// A single error, ready to be rendered against the source it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// A stable identifier for the class of error, e.g. `E0002`.
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub span: Option<Span>,
+    pub notes: Vec<String>,
+    /// Stack trace lines printed after the notes, innermost frame first.
+    pub trace: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(code: Option<&'static str>, message: String, span: Option<Span>) -> Self {
+        Diagnostic {
+            code,
+            message,
+            span,
+            notes: Vec::new(),
+            trace: Vec::new(),
+        }
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Converts any error produced while running a program into one or more
+    /// diagnostics, in source order.
+    pub fn from_error(err: &LoxError) -> Vec<Diagnostic> {
+        match err {
+            LoxError::Input(err) | LoxError::Io(err) => {
+                vec![Diagnostic::new(None, err.to_string(), None)]
+            }
+            LoxError::Compile(errors) => Diagnostic::from_compile_errors(errors),
+            LoxError::Runtime(err) => vec![err.into()],
+            LoxError::Exit(_) => Vec::new(),
+        }
+    }
+
+    fn from_compile_errors(errors: &CompileErrors) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = errors
+            .scanner
+            .iter()
+            .map(Diagnostic::from)
+            .chain(errors.parser.iter().map(Diagnostic::from))
+            .collect();
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span.map(|span| span.start));
+        diagnostics
+    }
+}
+
+impl From<&ScannerError> for Diagnostic {
+    fn from(err: &ScannerError) -> Self {
+        let diagnostic = Diagnostic::new(Some("E0001"), err.kind.to_string(), Some(err.span));
+        match err.kind {
+            ScannerErrorKind::UnterminatedString => {
+                diagnostic.with_note("help: add a closing '\"' to end the string")
+            }
+            _ => diagnostic,
+        }
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(err: &ParseError) -> Self {
+        Diagnostic::new(Some("E0002"), err.message.to_string(), Some(err.span))
+    }
+}
+
+/// Lists the frames of a stack trace. Deep traces, such as those from
+/// unbounded recursion, keep only the frames at either end.
+fn trace(frames: &[TraceFrame]) -> Vec<String> {
+    if frames.len() <= 2 * TRACE_ENDS {
+        return frames.iter().map(|frame| frame.to_string()).collect();
+    }
+    let first = frames[..TRACE_ENDS].iter().map(|frame| frame.to_string());
+    let last = frames[frames.len() - TRACE_ENDS..]
+        .iter()
+        .map(|frame| frame.to_string());
+    let skipped = format!("... {} more frames", frames.len() - 2 * TRACE_ENDS);
+    first.chain(std::iter::once(skipped)).chain(last).collect()
+}
+
+impl From<&RuntimeError> for Diagnostic {
+    fn from(err: &RuntimeError) -> Self {
+        let mut diagnostic = Diagnostic::new(Some("E0003"), err.message(), err.span());
+        diagnostic.trace = trace(&err.trace);
+        match err.kind.as_ref() {
+            ErrorKind::UndefinedVariable(_) => diagnostic
+                .with_note("help: variables must be declared with 'var' before they are used"),
+            ErrorKind::TypeMismatch { expected, .. } => {
+                diagnostic.with_note(format!("note: expected {}", expected))
+            }
+            _ => diagnostic,
+        }
+    }
+}
+
+/// Renders diagnostics as the offending source line, with the error's span
+/// underlined:
+///
+/// ```text
+/// error[E0002]: Expected expression.
+///  --> script.lox:1:9
+///   |
+/// 1 | print 1 +;
+///   |         ^
+/// ```
+pub struct Renderer<'a> {
+    source: &'a str,
+    file_name: &'a str,
+    color: bool,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(source: &'a str, file_name: &'a str) -> Self {
+        Renderer {
+            source,
+            file_name,
+            color: false,
+        }
+    }
+
+    /// Enables or disables ANSI colour codes in the output.
+    pub fn with_color(self, color: bool) -> Self {
+        Renderer { color, ..self }
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let mut out = String::new();
+        let code = diagnostic
+            .code
+            .map(|code| format!("[{}]", code))
+            .unwrap_or_default();
+        writeln!(
+            out,
+            "{}: {}",
+            self.paint(RED, &format!("error{}", code)),
+            self.paint(BOLD, &diagnostic.message)
+        )
+        .unwrap();
+
+        let gutter = diagnostic
+            .span
+            .map_or(1, |span| span.line.to_string().len());
+        let pad = " ".repeat(gutter);
+        match diagnostic.span {
+            Some(span) => {
+                writeln!(
+                    out,
+                    "{}{} {}:{}",
+                    pad,
+                    self.paint(BLUE, "-->"),
+                    self.file_name,
+                    span
+                )
+                .unwrap();
+                let line = self.source.lines().nth(span.line - 1).unwrap_or_default();
+                // Only the first line of a multi-line span is underlined.
+                let width = self
+                    .source
+                    .get(span.start..span.end)
+                    .unwrap_or_default()
+                    .chars()
+                    .take_while(|&c| c != '\n')
+                    .count()
+                    .max(1);
+                writeln!(out, "{} {}", pad, self.paint(BLUE, "|")).unwrap();
+                writeln!(
+                    out,
+                    "{} {} {}",
+                    self.paint(BLUE, &span.line.to_string()),
+                    self.paint(BLUE, "|"),
+                    line
+                )
+                .unwrap();
+                writeln!(
+                    out,
+                    "{} {} {}{}",
+                    pad,
+                    self.paint(BLUE, "|"),
+                    " ".repeat(span.column - 1),
+                    self.paint(RED, &"^".repeat(width))
+                )
+                .unwrap();
+            }
+            None => writeln!(out, "{}{} {}", pad, self.paint(BLUE, "-->"), self.file_name).unwrap(),
+        }
+
+        for note in &diagnostic.notes {
+            writeln!(out, "{} {} {}", pad, self.paint(BLUE, "="), note).unwrap();
+        }
+        for frame in &diagnostic.trace {
+            writeln!(out, "{}", frame).unwrap();
+        }
+        out
+    }
+
+    /// Wraps `text` in the given ANSI style when colour is enabled.
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+
+    #[test]
+    fn test_render_parse_error() {
+        let source = "var a = 1;\nprint a +;\n";
+        let (tokens, errors) = Scanner::new(source).scan_tokens();
+        let err = Parser::new(tokens, errors).parse().unwrap_err();
+        let diagnostics = Diagnostic::from_error(&LoxError::Compile(err));
+        let rendered = Renderer::new(source, "test.lox").render(&diagnostics[0]);
+        assert_eq!(
+            "error[E0002]: expected expression\n \
+             --> test.lox:2:10\n  \
+             |\n\
+             2 | print a +;\n  \
+             |          ^\n",
+            rendered
+        );
+    }
+
+    #[test]
+    fn test_render_without_span() {
+        let diagnostic = Diagnostic::new(None, String::from("No such file."), None);
+        let rendered = Renderer::new("", "test.lox").render(&diagnostic);
+        assert_eq!("error: No such file.\n --> test.lox\n", rendered);
+    }
+}
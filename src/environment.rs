@@ -1,11 +1,15 @@
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-use crate::{interpreter::RuntimeError, token::Literal};
+use crate::{
+    interpreter::{ErrorKind, RuntimeError},
+    token::Token,
+    value::Value,
+};
 
 #[derive(Debug, Clone)]
 pub struct Environment {
     pub enclosing: Option<Rc<RefCell<Environment>>>,
-    values: HashMap<String, Literal>,
+    values: HashMap<String, Value>,
 }
 
 impl Environment {
@@ -26,32 +30,82 @@ impl Environment {
     }
 
     /// Define a variable in the environment.
-    pub fn define(&mut self, name: String, value: Literal) {
+    pub fn define(&mut self, name: String, value: Value) {
         // Allow redefinition of variables.
         //
         // "When in doubt, do what Scheme does."
         self.values.insert(name, value);
     }
 
-    pub fn assign(&mut self, name: &str, value: Literal) -> Result<(), RuntimeError> {
-        if self.values.contains_key(name) {
-            self.define(name.to_string(), value);
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), RuntimeError> {
+        if self.values.contains_key(&name.lexeme) {
+            self.define(name.lexeme.clone(), value);
             Ok(())
         } else if let Some(enclosing) = &mut self.enclosing {
             enclosing.borrow_mut().assign(name, value)
         } else {
-            Err(RuntimeError::UndefinedVariable(name.to_string()))
+            Err(ErrorKind::UndefinedVariable(name.clone()).into())
+        }
+    }
+
+    /// Assign a variable in the environment `distance` levels up the
+    /// enclosing chain, as determined by the resolver.
+    pub fn assign_at(&mut self, distance: usize, name: &str, value: Value) {
+        if distance == 0 {
+            self.define(name.to_string(), value);
+        } else {
+            self.ancestor_enclosing(distance)
+                .borrow_mut()
+                .assign_at(0, name, value);
         }
     }
 
+    /// Lookup a variable in the environment `distance` levels up the
+    /// enclosing chain, as determined by the resolver.
+    pub fn get_at(&self, distance: usize, name: &str) -> Option<Value> {
+        if distance == 0 {
+            self.values.get(name).cloned()
+        } else {
+            self.ancestor_enclosing(distance).borrow().get_at(0, name)
+        }
+    }
+
+    /// Returns the environment `distance` levels up the enclosing chain, where
+    /// `distance` is at least one.
+    fn ancestor_enclosing(&self, distance: usize) -> Rc<RefCell<Environment>> {
+        let mut environment = Rc::clone(self.enclosing.as_ref().expect("resolved scope"));
+        for _ in 1..distance {
+            let enclosing = Rc::clone(
+                environment
+                    .borrow()
+                    .enclosing
+                    .as_ref()
+                    .expect("resolved scope"),
+            );
+            environment = enclosing;
+        }
+        environment
+    }
+
+    /// The variables defined directly in this environment, sorted by name.
+    pub fn bindings(&self) -> Vec<(&str, &Value)> {
+        let mut bindings: Vec<_> = self
+            .values
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+            .collect();
+        bindings.sort_by_key(|&(name, _)| name);
+        bindings
+    }
+
     /// Lookup a variable in the environment.
-    pub fn get(&self, name: &str) -> Result<Literal, RuntimeError> {
-        if let Some(value) = self.values.get(name).cloned() {
+    pub fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        if let Some(value) = self.values.get(&name.lexeme).cloned() {
             Ok(value)
         } else if let Some(enclosing) = &self.enclosing {
             enclosing.borrow().get(name)
         } else {
-            Err(RuntimeError::UndefinedVariable(name.to_string()))
+            Err(ErrorKind::UndefinedVariable(name.clone()).into())
         }
     }
 }
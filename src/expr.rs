@@ -1,6 +1,22 @@
-use std::fmt;
+use std::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
-use crate::token::*;
+use crate::{token::*, value::Value};
+
+/// Uniquely identifies an expression which refers to a variable, so that the
+/// resolver can record which scope the variable was bound in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExprId(usize);
+
+impl ExprId {
+    /// Returns a new ID, distinct from every other ID in this process.
+    pub fn fresh() -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        ExprId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
 
 #[derive(Debug, Clone)]
 pub enum Expr {
@@ -10,6 +26,13 @@ pub enum Expr {
     Unary(UnaryExpr),
     Variable(VariableExpr),
     Assign(AssignExpr),
+    Logical(LogicalExpr),
+    Call(CallExpr),
+    Get(GetExpr),
+    Set(SetExpr),
+    Index(IndexExpr),
+    This(ThisExpr),
+    Super(SuperExpr),
 }
 
 #[derive(Debug, Clone)]
@@ -22,11 +45,14 @@ pub struct BinaryExpr {
 #[derive(Debug, Clone)]
 pub struct GroupingExpr {
     pub expression: Box<Expr>,
+    /// The span including the enclosing parentheses.
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct LiteralExpr {
-    pub value: Literal,
+    pub value: Value,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -37,16 +63,86 @@ pub struct UnaryExpr {
 
 #[derive(Debug, Clone)]
 pub struct VariableExpr {
+    pub id: ExprId,
     pub name: Token,
 }
 
 #[derive(Debug, Clone)]
 pub struct AssignExpr {
+    pub id: ExprId,
+    pub name: Token,
+    pub value: Box<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogicalExpr {
+    pub left: Box<Expr>,
+    pub operator: Token,
+    pub right: Box<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CallExpr {
+    pub callee: Box<Expr>,
+    pub paren: Token,
+    pub arguments: Vec<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GetExpr {
+    pub object: Box<Expr>,
+    pub name: Token,
+}
+
+#[derive(Debug, Clone)]
+pub struct SetExpr {
+    pub object: Box<Expr>,
     pub name: Token,
     pub value: Box<Expr>,
 }
 
+/// A subscript, e.g. `list[0]`.
+#[derive(Debug, Clone)]
+pub struct IndexExpr {
+    pub object: Box<Expr>,
+    /// The closing bracket, where errors are reported.
+    pub bracket: Token,
+    pub index: Box<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ThisExpr {
+    pub id: ExprId,
+    pub keyword: Token,
+}
+
+#[derive(Debug, Clone)]
+pub struct SuperExpr {
+    pub id: ExprId,
+    pub keyword: Token,
+    pub method: Token,
+}
+
 impl Expr {
+    /// The region of source text this expression was parsed from.
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Binary(expr) => expr.left.span().to(expr.right.span()),
+            Expr::Grouping(expr) => expr.span,
+            Expr::Literal(expr) => expr.span,
+            Expr::Unary(expr) => expr.operator.span.to(expr.right.span()),
+            Expr::Variable(expr) => expr.name.span,
+            Expr::Assign(expr) => expr.name.span.to(expr.value.span()),
+            Expr::Logical(expr) => expr.left.span().to(expr.right.span()),
+            Expr::Call(expr) => expr.callee.span().to(expr.paren.span),
+            Expr::Get(expr) => expr.object.span().to(expr.name.span),
+            Expr::Set(expr) => expr.object.span().to(expr.value.span()),
+            Expr::Index(expr) => expr.object.span().to(expr.bracket.span),
+            Expr::This(expr) => expr.keyword.span,
+            Expr::Super(expr) => expr.keyword.span.to(expr.method.span),
+        }
+    }
+
     pub fn accept<Visitor: ExpressionVisitor>(&self, visitor: &mut Visitor) -> Visitor::Output {
         visitor.visit(self)
     }
@@ -63,6 +159,13 @@ pub trait ExpressionVisitor {
             Expr::Unary(expr) => self.visit_unary(expr),
             Expr::Variable(expr) => self.visit_variable(expr),
             Expr::Assign(expr) => self.visit_assign(expr),
+            Expr::Logical(expr) => self.visit_logical(expr),
+            Expr::Call(expr) => self.visit_call(expr),
+            Expr::Get(expr) => self.visit_get(expr),
+            Expr::Set(expr) => self.visit_set(expr),
+            Expr::Index(expr) => self.visit_index(expr),
+            Expr::This(expr) => self.visit_this(expr),
+            Expr::Super(expr) => self.visit_super(expr),
         }
     }
 
@@ -72,6 +175,13 @@ pub trait ExpressionVisitor {
     fn visit_unary(&mut self, expr: &UnaryExpr) -> Self::Output;
     fn visit_variable(&mut self, expr: &VariableExpr) -> Self::Output;
     fn visit_assign(&mut self, expr: &AssignExpr) -> Self::Output;
+    fn visit_logical(&mut self, expr: &LogicalExpr) -> Self::Output;
+    fn visit_call(&mut self, expr: &CallExpr) -> Self::Output;
+    fn visit_get(&mut self, expr: &GetExpr) -> Self::Output;
+    fn visit_set(&mut self, expr: &SetExpr) -> Self::Output;
+    fn visit_index(&mut self, expr: &IndexExpr) -> Self::Output;
+    fn visit_this(&mut self, expr: &ThisExpr) -> Self::Output;
+    fn visit_super(&mut self, expr: &SuperExpr) -> Self::Output;
 }
 
 struct Printer;
@@ -119,6 +229,47 @@ impl ExpressionVisitor for Printer {
     fn visit_assign(&mut self, expr: &AssignExpr) -> Self::Output {
         self.parenthesize(expr.name.lexeme.clone(), vec![*expr.value.clone()])
     }
+
+    fn visit_logical(&mut self, expr: &LogicalExpr) -> Self::Output {
+        self.parenthesize(
+            expr.operator.lexeme.clone(),
+            vec![*expr.left.clone(), *expr.right.clone()],
+        )
+    }
+
+    fn visit_call(&mut self, expr: &CallExpr) -> Self::Output {
+        let mut exprs = vec![*expr.callee.clone()];
+        exprs.extend(expr.arguments.iter().cloned());
+        self.parenthesize(String::from("call"), exprs)
+    }
+
+    fn visit_get(&mut self, expr: &GetExpr) -> Self::Output {
+        format!("(. {} {})", expr.object.accept(self), expr.name.lexeme)
+    }
+
+    fn visit_set(&mut self, expr: &SetExpr) -> Self::Output {
+        format!(
+            "(= {} {} {})",
+            expr.object.accept(self),
+            expr.name.lexeme,
+            expr.value.accept(self)
+        )
+    }
+
+    fn visit_index(&mut self, expr: &IndexExpr) -> Self::Output {
+        self.parenthesize(
+            String::from("[]"),
+            vec![*expr.object.clone(), *expr.index.clone()],
+        )
+    }
+
+    fn visit_this(&mut self, _expr: &ThisExpr) -> Self::Output {
+        String::from("this")
+    }
+
+    fn visit_super(&mut self, expr: &SuperExpr) -> Self::Output {
+        format!("(super {})", expr.method.lexeme)
+    }
 }
 
 impl fmt::Display for Expr {
@@ -135,16 +286,19 @@ mod tests {
     fn test_printer() {
         let exp = Expr::Binary(BinaryExpr {
             left: Box::new(Expr::Unary(UnaryExpr {
-                operator: Token::new(TokenType::Minus, "-".into(), None, 1),
+                operator: Token::new(TokenType::Minus, "-".into(), None, Span::default()),
                 right: Box::new(Expr::Literal(LiteralExpr {
-                    value: Literal::Number(123.0),
+                    value: Value::Number(123.0),
+                    span: Span::default(),
                 })),
             })),
-            operator: Token::new(TokenType::Star, "*".into(), None, 1),
+            operator: Token::new(TokenType::Star, "*".into(), None, Span::default()),
             right: Box::new(Expr::Grouping(GroupingExpr {
                 expression: Box::new(Expr::Literal(LiteralExpr {
-                    value: Literal::Number(45.67),
+                    value: Value::Number(45.67),
+                    span: Span::default(),
                 })),
+                span: Span::default(),
             })),
         });
         assert_eq!("(* (- 123) (group 45.67))", format!("{}", exp));
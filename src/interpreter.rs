@@ -1,191 +1,462 @@
 use core::fmt;
 use std::{
     cell::RefCell,
-    error::{self, Error},
-    fs,
-    io::{stdin, stdout, Write},
+    collections::HashMap,
+    error, fs,
+    io::{self, stderr, IsTerminal},
+    ops::{Deref, DerefMut},
     rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use crate::{
+    callable::{LoxFunction, NativeFn, NativeFunction},
+    class::{LoxClass, LoxInstance},
+    diagnostics::{Diagnostic, Renderer},
     environment::Environment,
-    expr::{self, ExpressionVisitor},
-    parser::Parser,
+    expr::{self, ExprId, ExpressionVisitor},
+    parser::{CompileErrors, Parser},
+    resolver::Resolver,
     scanner::Scanner,
-    stmt::{Stmt, StmtVisitor},
-    token::{self, Literal, TokenType},
+    stmt::{FunctionDecl, Stmt, StmtVisitor},
+    token::{self, Span, TokenType},
+    value::{ErrorValue, Value},
 };
 
+/// The deepest calls may be nested before a runtime error is raised.
+const MAX_FRAMES: usize = 10_000;
+
+/// The size of stack needed to run a script, with room for [`MAX_FRAMES`]
+/// nested calls.
+pub const STACK_SIZE: usize = 512 * 1024 * 1024;
+
 pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
     environment: Rc<RefCell<Environment>>,
+    /// The scope distance of each resolved local variable reference.
+    locals: HashMap<ExprId, usize>,
+    /// The name of the script being run, for stack traces.
+    file_name: String,
+    /// Every source compiled so far, indexed by [`SourceId`], since code
+    /// from any of them may still be called.
+    sources: Vec<Source>,
+    /// The functions currently being called, innermost last.
+    frames: Vec<CallFrame>,
+    /// Whether source is parsed in REPL mode. See [`Parser::with_repl`].
+    repl: bool,
 }
 
 impl Interpreter {
     pub fn new() -> Self {
-        Self {
-            environment: Rc::new(RefCell::new(Environment::new())),
-        }
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        let interpreter = Self {
+            environment: Rc::clone(&globals),
+            globals,
+            locals: HashMap::new(),
+            file_name: String::from("<script>"),
+            sources: Vec::new(),
+            frames: Vec::new(),
+            repl: false,
+        };
+        interpreter.define_native("clock", 0, |_, _| {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+            Ok(Value::Number(now.as_secs_f64()))
+        });
+        interpreter.define_native("env", 1, |interpreter, arguments| match &arguments[0] {
+            Value::String(name) => {
+                Ok(std::env::var(&**name).map_or(Value::Nil, |value| Value::String(value.into())))
+            }
+            value => Err(interpreter.native_error(format!(
+                "env() expects a string, not {}.",
+                value.type_name()
+            ))),
+        });
+        interpreter.define_native("exit", 1, |interpreter, arguments| match &arguments[0] {
+            Value::Number(code) if code.fract() == 0.0 => Err(ErrorKind::Exit(*code as i32).into()),
+            Value::Number(code) => {
+                Err(interpreter.native_error(format!("exit() expects an integer, not {}.", code)))
+            }
+            value => Err(interpreter.native_error(format!(
+                "exit() expects an integer, not {}.",
+                value.type_name()
+            ))),
+        });
+        interpreter.with_args(Vec::new())
     }
 
-    pub fn run_file(&mut self, filename: &str) -> Result<(), Box<dyn Error>> {
-        let source = fs::read_to_string(filename)?;
-        self.run(&source)
+    /// Defines the global `args` as a list of the given command-line
+    /// arguments.
+    pub fn with_args(self, args: Vec<String>) -> Self {
+        let args = args
+            .into_iter()
+            .map(|arg| Value::String(arg.into()))
+            .collect();
+        self.globals
+            .borrow_mut()
+            .define(String::from("args"), Value::List(Rc::new(args)));
+        self
     }
 
-    pub fn run_prompt(&mut self) -> Result<(), Box<dyn Error>> {
-        loop {
-            let mut input = String::new();
-            print!("> ");
-            stdout().flush()?;
-            stdin().read_line(&mut input)?;
-            if input.trim().is_empty() {
-                break;
-            }
-            self.run(&input)?;
-        }
-        Ok(())
+    fn define_native(&self, name: &'static str, arity: usize, function: NativeFn) {
+        self.globals.borrow_mut().define(
+            name.to_string(),
+            Value::Callable(Rc::new(NativeFunction::new(name, arity, function))),
+        );
     }
 
-    fn run(&mut self, source: &str) -> Result<(), Box<dyn Error>> {
-        let mut scanner = Scanner::new(source);
-        let tokens = scanner.scan_tokens()?;
-        let mut parser = Parser::new(tokens);
-        let stmts = parser.parse()?;
+    /// Creates an error raised by a native function, reported at the call
+    /// to the native function.
+    pub fn native_error(&self, message: String) -> RuntimeError {
+        let frame = self.frames.last().expect("native functions are called");
+        ErrorKind::Token(frame.paren.clone(), message).into()
+    }
+
+    /// Enables or disables REPL mode, in which the value of a trailing
+    /// expression is printed.
+    pub fn with_repl(self, repl: bool) -> Self {
+        Interpreter { repl, ..self }
+    }
+
+    /// Runs the script at `filename`, or on stdin if it is `-`, reporting any
+    /// errors to stderr. Scripts are always parsed strictly, even when loaded
+    /// into the REPL.
+    pub fn run_file(&mut self, filename: &str) -> Result<(), LoxError> {
+        let source = read_script(filename)?;
+        let repl = std::mem::replace(&mut self.repl, false);
+        let result = self.run_source(&source, script_name(filename));
+        self.repl = repl;
+        result
+    }
+
+    /// Runs `source`, which was read from `file_name`, reporting any errors
+    /// to stderr.
+    pub fn run_source(&mut self, source: &str, file_name: &str) -> Result<(), LoxError> {
+        self.file_name = file_name.to_string();
+        self.run(source).inspect_err(|err| self.report(err))
+    }
+
+    fn run(&mut self, source: &str) -> Result<(), LoxError> {
+        let stmts = self.compile(source, &self.file_name.clone())?;
         self.interpret(stmts)?;
         Ok(())
     }
 
+    /// Scans, parses and resolves `source`, which was read from `file_name`,
+    /// without running it.
+    pub fn compile(&mut self, source: &str, file_name: &str) -> Result<Vec<Stmt>, CompileErrors> {
+        let id = self.sources.len();
+        self.sources.push(Source {
+            name: file_name.to_string(),
+            text: source.into(),
+        });
+        let mut scanner = Scanner::new(source).with_source_id(id);
+        let (tokens, errors) = scanner.scan_tokens();
+        let mut parser = Parser::new(tokens, errors).with_repl(self.repl);
+        let stmts = parser.parse()?;
+        Resolver::new(self).resolve_program(&stmts)?;
+        Ok(stmts)
+    }
+
     fn interpret(&mut self, stmts: Vec<Stmt>) -> Result<(), RuntimeError> {
         for stmt in stmts {
-            stmt.accept(self)?;
+            stmt.accept(self).map_err(|mut err| {
+                if let (true, Some(span)) = (err.trace.is_empty(), err.span()) {
+                    err.unwind(None, self.source_name(span), span.line);
+                }
+                err
+            })?;
         }
         Ok(())
     }
 
-    /// Executes a block of code with its own environment.
-    fn execute_block(&mut self, block: &[Stmt]) -> Result<(), RuntimeError> {
-        let environment = Rc::clone(&self.environment);
-        self.environment = Rc::new(RefCell::new(Environment::new_enclosed(environment)));
-        for stmt in block {
-            stmt.accept(self)?;
+    /// Prints the diagnostics for `err` to stderr.
+    fn report(&self, err: &LoxError) {
+        eprint!("{}", self.render(err, use_color()));
+    }
+
+    /// Renders the diagnostics for `err`, each against the source it was
+    /// raised in. Errors in code from an earlier REPL line or a loaded file
+    /// are raised in that source rather than the current one.
+    fn render(&self, err: &LoxError, color: bool) -> String {
+        Diagnostic::from_error(err)
+            .iter()
+            .map(|diagnostic| {
+                let source = match diagnostic.span {
+                    Some(span) => self.sources.get(span.source),
+                    None => self.sources.last(),
+                };
+                let (text, name) = source.map_or(("", self.file_name.as_str()), |source| {
+                    (&*source.text, source.name.as_str())
+                });
+                Renderer::new(text, name)
+                    .with_color(color)
+                    .render(diagnostic)
+            })
+            .collect()
+    }
+
+    /// The environment in which code is currently being run.
+    pub fn environment(&self) -> Rc<RefCell<Environment>> {
+        Rc::clone(&self.environment)
+    }
+
+    /// Records the number of scopes between a variable reference and the
+    /// scope in which the variable is declared.
+    pub fn resolve(&mut self, id: ExprId, depth: usize) {
+        self.locals.insert(id, depth);
+    }
+
+    /// Lookup a variable using its resolved scope distance, falling back to
+    /// the globals for unresolved references.
+    fn lookup_variable(&self, id: ExprId, name: &token::Token) -> Result<Value, RuntimeError> {
+        match self.locals.get(&id) {
+            Some(&distance) => self
+                .environment
+                .borrow()
+                .get_at(distance, &name.lexeme)
+                .ok_or_else(|| ErrorKind::UndefinedVariable(name.clone()).into()),
+            None => self.globals.borrow().get(name),
+        }
+    }
+
+    /// Executes a block of code in the given environment, restoring the
+    /// current environment afterwards however the block is left.
+    pub fn execute_block(
+        &mut self,
+        block: &[Stmt],
+        environment: Environment,
+    ) -> Result<(), RuntimeError> {
+        let mut scope = self.enter_scope(environment);
+        block.iter().try_for_each(|stmt| stmt.accept(&mut *scope))
+    }
+
+    /// Makes `environment` the current environment until the returned guard
+    /// is dropped.
+    fn enter_scope(&mut self, environment: Environment) -> ScopeGuard<'_> {
+        let previous = std::mem::replace(&mut self.environment, Rc::new(RefCell::new(environment)));
+        ScopeGuard {
+            interpreter: self,
+            previous,
         }
-        self.environment = Rc::clone(&self.environment)
-            .borrow()
-            .enclosing
-            .clone()
-            .unwrap();
-        Ok(())
+    }
+
+    /// The name of the file the code at `span` was read from.
+    fn source_name(&self, span: Span) -> &str {
+        self.sources
+            .get(span.source)
+            .map_or(self.file_name.as_str(), |source| source.name.as_str())
+    }
+
+    /// Runs `call` in a new frame named `function`, recording the frame in
+    /// the trace of any error which unwinds out of it.
+    fn call_frame<F>(
+        &mut self,
+        function: &str,
+        paren: &token::Token,
+        call: F,
+    ) -> Result<Value, RuntimeError>
+    where
+        F: FnOnce(&mut Self) -> Result<Value, RuntimeError>,
+    {
+        if self.frames.len() >= MAX_FRAMES {
+            return Err(ErrorKind::Token(paren.clone(), String::from("Stack overflow.")).into());
+        }
+        self.frames.push(CallFrame {
+            function: function.to_string(),
+            paren: paren.clone(),
+        });
+        let result = call(self);
+        self.frames.pop();
+        result.map_err(|mut err| {
+            // The frame the error was raised in is recorded by whichever
+            // frame it first unwinds into.
+            if err.trace.is_empty() {
+                let span = err.span().unwrap_or(paren.span);
+                err.unwind(Some(function), self.source_name(span), span.line);
+            }
+            let caller = self.frames.last().map(|frame| frame.function.as_str());
+            err.unwind(caller, self.source_name(paren.span), paren.span.line);
+            err
+        })
+    }
+}
+
+/// Source text which has been compiled, along with where it was read from.
+struct Source {
+    name: String,
+    text: Rc<str>,
+}
+
+/// A call which is in progress.
+struct CallFrame {
+    function: String,
+    /// The closing parenthesis of the call expression.
+    paren: token::Token,
+}
+
+/// Restores the environment which was current before a scope was entered
+/// when dropped, so that every exit path from the scope, including errors,
+/// `return` and panics, leaves the interpreter in the enclosing scope.
+struct ScopeGuard<'a> {
+    interpreter: &'a mut Interpreter,
+    previous: Rc<RefCell<Environment>>,
+}
+
+impl Deref for ScopeGuard<'_> {
+    type Target = Interpreter;
+
+    fn deref(&self) -> &Interpreter {
+        self.interpreter
+    }
+}
+
+impl DerefMut for ScopeGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Interpreter {
+        self.interpreter
     }
 }
 
+impl Drop for ScopeGuard<'_> {
+    fn drop(&mut self) {
+        self.interpreter.environment = Rc::clone(&self.previous);
+    }
+}
+
+/// Prints the diagnostics for `err` to stderr.
+pub fn report(err: &LoxError, source: &str, file_name: &str) {
+    let renderer = Renderer::new(source, file_name).with_color(use_color());
+    for diagnostic in Diagnostic::from_error(err) {
+        eprint!("{}", renderer.render(&diagnostic));
+    }
+}
+
+/// The path which stands for stdin in place of a script.
+pub const STDIN: &str = "-";
+
+/// Reads the script at `filename`, or stdin if it is [`STDIN`], reporting any
+/// error to stderr.
+pub fn read_script(filename: &str) -> Result<String, LoxError> {
+    let source = if filename == STDIN {
+        io::read_to_string(io::stdin())
+    } else {
+        fs::read_to_string(filename)
+    };
+    source
+        .map_err(LoxError::Input)
+        .inspect_err(|err| report(err, "", script_name(filename)))
+}
+
+/// The name of the script at `filename` in diagnostics.
+pub fn script_name(filename: &str) -> &str {
+    if filename == STDIN {
+        "<stdin>"
+    } else {
+        filename
+    }
+}
+
+/// Whether diagnostics should be coloured, following the `NO_COLOR`
+/// convention.
+fn use_color() -> bool {
+    stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
 impl Interpreter {
-    fn is_truthy(&self, literal: &Literal) -> bool {
-        match literal {
-            Literal::Nil => false,
-            Literal::Boolean(b) => *b,
+    fn check_arity(
+        &self,
+        paren: &token::Token,
+        expected: usize,
+        got: usize,
+    ) -> Result<(), RuntimeError> {
+        if expected == got {
+            Ok(())
+        } else {
+            Err(ErrorKind::Arity(paren.clone(), expected, got).into())
+        }
+    }
+
+    /// Unwraps the operands of an arithmetic or comparison operator, which
+    /// must both be numbers.
+    fn number_operands(
+        &self,
+        operator: &token::Token,
+        left: &Value,
+        right: &Value,
+    ) -> Result<(f64, f64), RuntimeError> {
+        match (left, right) {
+            (Value::Number(left), Value::Number(right)) => Ok((*left, *right)),
+            _ => Err(ErrorKind::TypeMismatch {
+                operator: operator.clone(),
+                expected: "numbers",
+                actual: vec![left.type_name(), right.type_name()],
+            }
+            .into()),
+        }
+    }
+
+    fn is_truthy(&self, value: &Value) -> bool {
+        match value {
+            Value::Nil => false,
+            Value::Boolean(b) => *b,
             _ => true,
         }
     }
 }
 
 impl ExpressionVisitor for Interpreter {
-    type Output = Result<Literal, RuntimeError>;
+    type Output = Result<Value, RuntimeError>;
 
     fn visit_binary(&mut self, expr: &expr::BinaryExpr) -> Self::Output {
         let left = expr.left.accept(self)?;
         let right = expr.right.accept(self)?;
-        match expr.operator.typ {
+        let operator = &expr.operator;
+        match operator.typ {
             TokenType::Minus => {
-                if let (Literal::Number(left), Literal::Number(right)) = (left, right) {
-                    Ok(Literal::Number(left - right))
-                } else {
-                    Err(RuntimeError::Token(
-                        expr.operator.clone(),
-                        "Operands must be numbers.",
-                    ))
-                }
+                let (left, right) = self.number_operands(operator, &left, &right)?;
+                Ok(Value::Number(left - right))
             }
             TokenType::Slash => {
-                if let (Literal::Number(left), Literal::Number(right)) = (left, right) {
-                    if right == 0.0 {
-                        Err(RuntimeError::Token(
-                            expr.operator.clone(),
-                            "Cannot divide by zero.",
-                        ))
-                    } else {
-                        Ok(Literal::Number(left / right))
-                    }
+                let (left, right) = self.number_operands(operator, &left, &right)?;
+                if right == 0.0 {
+                    Err(ErrorKind::DivisionByZero(operator.clone()).into())
                 } else {
-                    Err(RuntimeError::Token(
-                        expr.operator.clone(),
-                        "Operands must be numbers.",
-                    ))
+                    Ok(Value::Number(left / right))
                 }
             }
             TokenType::Star => {
-                if let (Literal::Number(left), Literal::Number(right)) = (left, right) {
-                    Ok(Literal::Number(left * right))
-                } else {
-                    Err(RuntimeError::Token(
-                        expr.operator.clone(),
-                        "Operands must be numbers.",
-                    ))
-                }
+                let (left, right) = self.number_operands(operator, &left, &right)?;
+                Ok(Value::Number(left * right))
             }
-            TokenType::Plus => {
-                if let (Literal::Number(left), Literal::Number(right)) = (&left, &right) {
-                    Ok(Literal::Number(left + right))
-                } else if let (Literal::String(left), Literal::String(right)) = (&left, &right) {
-                    Ok(Literal::String(left.clone() + right))
-                } else {
-                    Err(RuntimeError::Token(
-                        expr.operator.clone(),
-                        "Operands must be two numbers or two strings.",
-                    ))
+            TokenType::Plus => match (&left, &right) {
+                (Value::Number(left), Value::Number(right)) => Ok(Value::Number(left + right)),
+                (Value::String(left), Value::String(right)) => {
+                    Ok(Value::String(format!("{}{}", left, right).into()))
                 }
-            }
-            TokenType::Greater => {
-                if let (Literal::Number(left), Literal::Number(right)) = (left, right) {
-                    Ok(Literal::Boolean(left > right))
-                } else {
-                    Err(RuntimeError::Token(
-                        expr.operator.clone(),
-                        "Operands must be numbers.",
-                    ))
+                _ => Err(ErrorKind::TypeMismatch {
+                    operator: operator.clone(),
+                    expected: "two numbers or two strings",
+                    actual: vec![left.type_name(), right.type_name()],
                 }
+                .into()),
+            },
+            TokenType::Greater => {
+                let (left, right) = self.number_operands(operator, &left, &right)?;
+                Ok(Value::Boolean(left > right))
             }
             TokenType::GreaterEqual => {
-                if let (Literal::Number(left), Literal::Number(right)) = (left, right) {
-                    Ok(Literal::Boolean(left >= right))
-                } else {
-                    Err(RuntimeError::Token(
-                        expr.operator.clone(),
-                        "Operands must be numbers.",
-                    ))
-                }
+                let (left, right) = self.number_operands(operator, &left, &right)?;
+                Ok(Value::Boolean(left >= right))
             }
             TokenType::Less => {
-                if let (Literal::Number(left), Literal::Number(right)) = (left, right) {
-                    Ok(Literal::Boolean(left < right))
-                } else {
-                    Err(RuntimeError::Token(
-                        expr.operator.clone(),
-                        "Operands must be numbers.",
-                    ))
-                }
+                let (left, right) = self.number_operands(operator, &left, &right)?;
+                Ok(Value::Boolean(left < right))
             }
             TokenType::LessEqual => {
-                if let (Literal::Number(left), Literal::Number(right)) = (left, right) {
-                    Ok(Literal::Boolean(left <= right))
-                } else {
-                    Err(RuntimeError::Token(
-                        expr.operator.clone(),
-                        "Operands must be numbers.",
-                    ))
-                }
+                let (left, right) = self.number_operands(operator, &left, &right)?;
+                Ok(Value::Boolean(left <= right))
             }
-            TokenType::BangEqual => Ok(Literal::Boolean(left != right)),
-            TokenType::EqualEqual => Ok(Literal::Boolean(left == right)),
+            TokenType::BangEqual => Ok(Value::Boolean(left != right)),
+            TokenType::EqualEqual => Ok(Value::Boolean(left == right)),
             _ => unreachable!(),
         }
     }
@@ -202,31 +473,176 @@ impl ExpressionVisitor for Interpreter {
         let right = expr.right.accept(self)?;
         match expr.operator.typ {
             TokenType::Minus => {
-                if let Literal::Number(right) = right {
-                    Ok(Literal::Number(-right))
+                if let Value::Number(right) = right {
+                    Ok(Value::Number(-right))
                 } else {
-                    Err(RuntimeError::Token(
-                        expr.operator.clone(),
-                        "Invalid operand for unary minus",
-                    ))
+                    Err(ErrorKind::TypeMismatch {
+                        operator: expr.operator.clone(),
+                        expected: "a number",
+                        actual: vec![right.type_name()],
+                    }
+                    .into())
                 }
             }
-            TokenType::Bang => Ok(Literal::Boolean(!self.is_truthy(&right))),
+            TokenType::Bang => Ok(Value::Boolean(!self.is_truthy(&right))),
             _ => unreachable!(),
         }
     }
 
     fn visit_variable(&mut self, expr: &expr::VariableExpr) -> Self::Output {
-        self.environment.borrow().get(&expr.name.lexeme)
+        self.lookup_variable(expr.id, &expr.name)
     }
 
     fn visit_assign(&mut self, expr: &expr::AssignExpr) -> Self::Output {
         let value = expr.value.accept(self)?;
-        self.environment
-            .borrow_mut()
-            .assign(&expr.name.lexeme, value.clone())?;
+        match self.locals.get(&expr.id) {
+            Some(&distance) => {
+                self.environment
+                    .borrow_mut()
+                    .assign_at(distance, &expr.name.lexeme, value.clone());
+            }
+            None => {
+                self.globals
+                    .borrow_mut()
+                    .assign(&expr.name, value.clone())?;
+            }
+        }
         Ok(value)
     }
+
+    fn visit_logical(&mut self, expr: &expr::LogicalExpr) -> Self::Output {
+        let left = expr.left.accept(self)?;
+        let short_circuits = match expr.operator.typ {
+            TokenType::Or => self.is_truthy(&left),
+            TokenType::And => !self.is_truthy(&left),
+            _ => unreachable!(),
+        };
+        if short_circuits {
+            Ok(left)
+        } else {
+            expr.right.accept(self)
+        }
+    }
+
+    fn visit_call(&mut self, expr: &expr::CallExpr) -> Self::Output {
+        let callee = expr.callee.accept(self)?;
+        let arguments = expr
+            .arguments
+            .iter()
+            .map(|argument| argument.accept(self))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match callee {
+            Value::Callable(function) => {
+                self.check_arity(&expr.paren, function.arity(), arguments.len())?;
+                self.call_frame(function.name(), &expr.paren, |interpreter| {
+                    function.call(interpreter, arguments)
+                })
+            }
+            Value::Class(class) => {
+                self.check_arity(&expr.paren, class.arity(), arguments.len())?;
+                self.call_frame(&class.name, &expr.paren, |interpreter| {
+                    LoxClass::instantiate(&class, interpreter, arguments)
+                })
+            }
+            callee => Err(ErrorKind::Token(
+                expr.paren.clone(),
+                format!(
+                    "Can only call functions and classes, not {}.",
+                    callee.type_name()
+                ),
+            )
+            .into()),
+        }
+    }
+
+    fn visit_get(&mut self, expr: &expr::GetExpr) -> Self::Output {
+        match expr.object.accept(self)? {
+            Value::Instance(instance) => LoxInstance::get(&instance, &expr.name),
+            Value::Error(error) => error.get(&expr.name),
+            Value::List(values) if expr.name.lexeme == "length" => {
+                Ok(Value::Number(values.len() as f64))
+            }
+            Value::List(_) => Err(ErrorKind::UndefinedProperty(expr.name.clone()).into()),
+            object => Err(ErrorKind::Token(
+                expr.name.clone(),
+                format!(
+                    "Only instances have properties, not {}.",
+                    object.type_name()
+                ),
+            )
+            .into()),
+        }
+    }
+
+    fn visit_set(&mut self, expr: &expr::SetExpr) -> Self::Output {
+        let instance = match expr.object.accept(self)? {
+            Value::Instance(instance) => instance,
+            object => {
+                return Err(ErrorKind::Token(
+                    expr.name.clone(),
+                    format!("Only instances have fields, not {}.", object.type_name()),
+                )
+                .into())
+            }
+        };
+        let value = expr.value.accept(self)?;
+        instance.borrow_mut().set(&expr.name, value.clone());
+        Ok(value)
+    }
+
+    fn visit_index(&mut self, expr: &expr::IndexExpr) -> Self::Output {
+        let object = expr.object.accept(self)?;
+        let index = expr.index.accept(self)?;
+        let Value::List(values) = object else {
+            return Err(ErrorKind::Token(
+                expr.bracket.clone(),
+                format!("Only lists can be indexed, not {}.", object.type_name()),
+            )
+            .into());
+        };
+        match index {
+            Value::Number(i) if i.fract() == 0.0 && i >= 0.0 && (i as usize) < values.len() => {
+                Ok(values[i as usize].clone())
+            }
+            Value::Number(i) => Err(ErrorKind::Token(
+                expr.bracket.clone(),
+                format!(
+                    "Index {} is out of bounds for a list of length {}.",
+                    i,
+                    values.len()
+                ),
+            )
+            .into()),
+            index => Err(ErrorKind::Token(
+                expr.bracket.clone(),
+                format!("List indices must be numbers, not {}.", index.type_name()),
+            )
+            .into()),
+        }
+    }
+
+    fn visit_this(&mut self, expr: &expr::ThisExpr) -> Self::Output {
+        self.lookup_variable(expr.id, &expr.keyword)
+    }
+
+    fn visit_super(&mut self, expr: &expr::SuperExpr) -> Self::Output {
+        // The resolver guarantees `super` is bound, and that `this` is bound
+        // in the scope just inside it.
+        let distance = self.locals[&expr.id];
+        let environment = self.environment.borrow();
+        let Some(Value::Class(superclass)) = environment.get_at(distance, &expr.keyword.lexeme)
+        else {
+            unreachable!("'super' is always bound to a class");
+        };
+        let Some(Value::Instance(instance)) = environment.get_at(distance - 1, "this") else {
+            unreachable!("'this' is always bound to an instance");
+        };
+        match superclass.find_method(&expr.method.lexeme) {
+            Some(method) => Ok(Value::Callable(Rc::new(method.bind(instance)))),
+            None => Err(ErrorKind::UndefinedProperty(expr.method.clone()).into()),
+        }
+    }
 }
 
 impl StmtVisitor for Interpreter {
@@ -247,7 +663,7 @@ impl StmtVisitor for Interpreter {
             .as_ref()
             .map(|expr| expr.accept(self))
             .transpose()?
-            .unwrap_or(Literal::Nil);
+            .unwrap_or(Value::Nil);
         self.environment
             .borrow_mut()
             .define(name.lexeme.clone(), value);
@@ -255,24 +671,767 @@ impl StmtVisitor for Interpreter {
     }
 
     fn visit_block(&mut self, statements: &[Stmt]) -> Self::Output {
-        self.execute_block(statements)
+        let environment = Environment::new_enclosed(Rc::clone(&self.environment));
+        self.execute_block(statements, environment)
+    }
+
+    fn visit_if(
+        &mut self,
+        condition: &expr::Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> Self::Output {
+        let condition = condition.accept(self)?;
+        if self.is_truthy(&condition) {
+            then_branch.accept(self)
+        } else if let Some(else_branch) = else_branch {
+            else_branch.accept(self)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn visit_while(
+        &mut self,
+        condition: &expr::Expr,
+        body: &Stmt,
+        increment: Option<&expr::Expr>,
+        label: Option<&token::Token>,
+    ) -> Self::Output {
+        // Whether a `break` or `continue` applies to this loop.
+        let targets = |target: &Option<token::Token>| match (target, label) {
+            (None, _) => true,
+            (Some(target), Some(label)) => target.lexeme == label.lexeme,
+            (Some(_), None) => false,
+        };
+        loop {
+            let value = condition.accept(self)?;
+            if !self.is_truthy(&value) {
+                return Ok(());
+            }
+            if let Err(err) = body.accept(self) {
+                match err.kind.as_ref() {
+                    ErrorKind::Break(_, target) if targets(target) => return Ok(()),
+                    ErrorKind::Continue(_, target) if targets(target) => {}
+                    _ => return Err(err),
+                }
+            }
+            if let Some(increment) = increment {
+                increment.accept(self)?;
+            }
+        }
+    }
+
+    fn visit_function(&mut self, declaration: &Rc<FunctionDecl>) -> Self::Output {
+        let function =
+            LoxFunction::new(Rc::clone(declaration), Rc::clone(&self.environment), false);
+        self.environment.borrow_mut().define(
+            declaration.name.lexeme.clone(),
+            Value::Callable(Rc::new(function)),
+        );
+        Ok(())
+    }
+
+    fn visit_return(
+        &mut self,
+        _keyword: &token::Token,
+        value: &Option<expr::Expr>,
+    ) -> Self::Output {
+        let value = value
+            .as_ref()
+            .map(|expr| expr.accept(self))
+            .transpose()?
+            .unwrap_or(Value::Nil);
+        Err(ErrorKind::Return(value).into())
+    }
+
+    fn visit_break(
+        &mut self,
+        keyword: &token::Token,
+        label: Option<&token::Token>,
+    ) -> Self::Output {
+        Err(ErrorKind::Break(keyword.clone(), label.cloned()).into())
+    }
+
+    fn visit_continue(
+        &mut self,
+        keyword: &token::Token,
+        label: Option<&token::Token>,
+    ) -> Self::Output {
+        Err(ErrorKind::Continue(keyword.clone(), label.cloned()).into())
+    }
+
+    fn visit_class(
+        &mut self,
+        name: &token::Token,
+        superclass: Option<&expr::VariableExpr>,
+        methods: &[Rc<FunctionDecl>],
+    ) -> Self::Output {
+        let superclass = match superclass {
+            Some(superclass) => match self.visit_variable(superclass)? {
+                Value::Class(class) => Some(class),
+                value => {
+                    return Err(ErrorKind::Token(
+                        superclass.name.clone(),
+                        format!("Superclass must be a class, not {}.", value.type_name()),
+                    )
+                    .into())
+                }
+            },
+            None => None,
+        };
+
+        // Methods of a subclass close over an environment binding `super`.
+        let closure = match &superclass {
+            Some(superclass) => {
+                let mut environment = Environment::new_enclosed(Rc::clone(&self.environment));
+                environment.define(String::from("super"), Value::Class(Rc::clone(superclass)));
+                Rc::new(RefCell::new(environment))
+            }
+            None => Rc::clone(&self.environment),
+        };
+
+        let methods = methods
+            .iter()
+            .map(|method| {
+                let function = LoxFunction::new(
+                    Rc::clone(method),
+                    Rc::clone(&closure),
+                    method.name.lexeme == "init",
+                );
+                (method.name.lexeme.clone(), Rc::new(function))
+            })
+            .collect();
+        let class = LoxClass::new(name.lexeme.clone(), superclass, methods);
+        self.environment
+            .borrow_mut()
+            .define(name.lexeme.clone(), Value::Class(Rc::new(class)));
+        Ok(())
+    }
+
+    fn visit_throw(&mut self, keyword: &token::Token, value: &expr::Expr) -> Self::Output {
+        let value = value.accept(self)?;
+        Err(ErrorKind::Throw(keyword.clone(), value).into())
+    }
+
+    fn visit_try(
+        &mut self,
+        body: &[Stmt],
+        catch: Option<&(token::Token, Vec<Stmt>)>,
+        finally: Option<&[Stmt]>,
+    ) -> Self::Output {
+        let result = match (self.visit_block(body), catch) {
+            (Err(err), Some((name, body))) if err.is_catchable() => {
+                let mut environment = Environment::new_enclosed(Rc::clone(&self.environment));
+                environment.define(name.lexeme.clone(), err.into_value());
+                self.execute_block(body, environment)
+            }
+            (result, _) => result,
+        };
+        // An error or `return` from the finally block replaces the result of
+        // the rest of the statement.
+        if let Some(finally) = finally {
+            self.visit_block(finally)?;
+        }
+        result
     }
 }
 
+/// Any error which stops a script from running to completion.
 #[derive(Debug)]
-pub enum RuntimeError {
-    Token(token::Token, &'static str),
-    UndefinedVariable(String),
+pub enum LoxError {
+    /// The script could not be read.
+    Input(io::Error),
+    /// The REPL could not read input or save its history.
+    Io(io::Error),
+    /// Lexical, syntax or static errors found before the script ran.
+    Compile(CompileErrors),
+    Runtime(RuntimeError),
+    /// The script called `exit` with the given code.
+    Exit(i32),
+}
+
+impl LoxError {
+    /// The process exit code for this error, following the conventions of
+    /// the reference Lox implementations.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LoxError::Input(_) => exitcode::NOINPUT,
+            LoxError::Io(_) => exitcode::IOERR,
+            LoxError::Compile(_) => exitcode::DATAERR,
+            LoxError::Runtime(_) => exitcode::SOFTWARE,
+            LoxError::Exit(code) => *code,
+        }
+    }
+}
+
+impl From<io::Error> for LoxError {
+    fn from(err: io::Error) -> Self {
+        LoxError::Io(err)
+    }
+}
+
+impl From<CompileErrors> for LoxError {
+    fn from(errors: CompileErrors) -> Self {
+        LoxError::Compile(errors)
+    }
+}
+
+impl From<RuntimeError> for LoxError {
+    fn from(err: RuntimeError) -> Self {
+        match *err.kind {
+            ErrorKind::Exit(code) => LoxError::Exit(code),
+            _ => LoxError::Runtime(err),
+        }
+    }
+}
+
+impl error::Error for LoxError {}
+
+impl fmt::Display for LoxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoxError::Input(err) | LoxError::Io(err) => write!(f, "{}", err),
+            LoxError::Compile(errors) => write!(f, "{}", errors),
+            LoxError::Runtime(err) => write!(f, "{}", err),
+            LoxError::Exit(code) => write!(f, "Exited with code {}.", code),
+        }
+    }
+}
+
+/// An error raised while a script is running, along with the call stack at
+/// the point it was raised.
+#[derive(Debug)]
+pub struct RuntimeError {
+    /// Boxed, since errors are returned on every path through the
+    /// interpreter but only rarely raised.
+    pub kind: Box<ErrorKind>,
+    /// The frames which were active when the error was raised, innermost
+    /// first. Empty until the error has unwound out of the frame it was
+    /// raised in.
+    pub trace: Vec<TraceFrame>,
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    /// An error described by a message, at `token`.
+    Token(token::Token, String),
+    /// An operator applied to operands of the wrong types. `expected`
+    /// describes the operands the operator accepts, and `actual` names the
+    /// types of the operands it was given, left to right.
+    TypeMismatch {
+        operator: token::Token,
+        expected: &'static str,
+        actual: Vec<&'static str>,
+    },
+    DivisionByZero(token::Token),
+    UndefinedVariable(token::Token),
+    /// A call with the wrong number of arguments: `(paren, expected, got)`.
+    Arity(token::Token, usize, usize),
+    UndefinedProperty(token::Token),
+    /// A value thrown by a `throw` statement.
+    Throw(token::Token, Value),
+    /// Not an error: unwinds the stack out of a function body when a
+    /// `return` statement is executed.
+    Return(Value),
+    /// Not an error: unwinds the whole stack when the script calls `exit`,
+    /// so that the process exits with the given code.
+    Exit(i32),
+    /// Not an error: unwinds out of the innermost loop, or the loop with the
+    /// given label, when a `break` statement is executed.
+    Break(token::Token, Option<token::Token>),
+    /// Not an error: unwinds to the next iteration of a loop when a
+    /// `continue` statement is executed.
+    Continue(token::Token, Option<token::Token>),
+}
+
+impl From<ErrorKind> for RuntimeError {
+    fn from(kind: ErrorKind) -> Self {
+        RuntimeError {
+            kind: Box::new(kind),
+            trace: Vec::new(),
+        }
+    }
 }
 
 impl error::Error for RuntimeError {}
 
+impl RuntimeError {
+    /// The location in the source at which the error occurred, if known.
+    pub fn span(&self) -> Option<Span> {
+        match self.kind.as_ref() {
+            ErrorKind::Token(token, _)
+            | ErrorKind::TypeMismatch {
+                operator: token, ..
+            }
+            | ErrorKind::DivisionByZero(token)
+            | ErrorKind::UndefinedVariable(token)
+            | ErrorKind::Arity(token, _, _)
+            | ErrorKind::UndefinedProperty(token)
+            | ErrorKind::Throw(token, _)
+            | ErrorKind::Break(token, _)
+            | ErrorKind::Continue(token, _) => Some(token.span),
+            ErrorKind::Return(_) | ErrorKind::Exit(_) => None,
+        }
+    }
+
+    /// A description of the error, without its location.
+    pub fn message(&self) -> String {
+        match self.kind.as_ref() {
+            ErrorKind::Token(_, message) => message.clone(),
+            ErrorKind::TypeMismatch {
+                operator, actual, ..
+            } => match (operator.typ, actual.as_slice()) {
+                (TokenType::Minus, [operand]) => format!("cannot negate {}", operand),
+                (TokenType::Minus, [left, right]) => {
+                    format!("cannot subtract {} from {}", right, left)
+                }
+                (TokenType::Plus, [left, right]) => format!("cannot add {} to {}", right, left),
+                (TokenType::Star, [left, right]) => {
+                    format!("cannot multiply {} by {}", left, right)
+                }
+                (TokenType::Slash, [left, right]) => format!("cannot divide {} by {}", left, right),
+                (_, [left, right]) => format!("cannot compare {} with {}", left, right),
+                _ => format!("invalid operands for '{}'", operator.lexeme),
+            },
+            ErrorKind::DivisionByZero(_) => String::from("cannot divide by zero"),
+            ErrorKind::UndefinedVariable(name) => {
+                format!("Undefined variable '{}'.", name.lexeme)
+            }
+            ErrorKind::Arity(_, expected, got) => {
+                format!("Expected {} arguments but got {}.", expected, got)
+            }
+            ErrorKind::UndefinedProperty(name) => {
+                format!("Undefined property '{}'.", name.lexeme)
+            }
+            ErrorKind::Throw(_, Value::Error(error)) => error.message.clone(),
+            ErrorKind::Throw(_, value) => format!("Uncaught exception: {}", value),
+            ErrorKind::Return(_) => String::from("Can't return from top-level code."),
+            ErrorKind::Exit(code) => format!("Exited with code {}.", code),
+            ErrorKind::Break(keyword, _) | ErrorKind::Continue(keyword, _) => {
+                format!("Can't use '{}' outside of a loop.", keyword.lexeme)
+            }
+        }
+    }
+
+    /// Whether a `catch` clause may catch the error. Control flow which
+    /// unwinds the stack, such as `return`, cannot be caught.
+    pub fn is_catchable(&self) -> bool {
+        !matches!(
+            *self.kind,
+            ErrorKind::Return(_)
+                | ErrorKind::Break(..)
+                | ErrorKind::Continue(..)
+                | ErrorKind::Exit(_)
+        )
+    }
+
+    /// Converts the error into the value bound by a `catch` clause. Thrown
+    /// values are caught as they are; built-in errors are wrapped in an
+    /// [`ErrorValue`].
+    fn into_value(self) -> Value {
+        let line = self.span().map_or(0, |span| span.line);
+        let message = self.message();
+        match *self.kind {
+            ErrorKind::Throw(_, value) => value,
+            _ => Value::Error(Rc::new(ErrorValue { message, line })),
+        }
+    }
+
+    /// Records that the error has unwound out of `function` (or the top-level
+    /// script when `None`), having last executed `line`.
+    fn unwind(&mut self, function: Option<&str>, file: &str, line: usize) {
+        self.trace.push(TraceFrame {
+            function: function.map(String::from),
+            file: file.to_string(),
+            line,
+        });
+    }
+}
+
 impl fmt::Display for RuntimeError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let message = match self {
-            RuntimeError::Token(token, message) => format!("{}: {}", token, message),
-            RuntimeError::UndefinedVariable(name) => format!("Undefined variable '{}'.", name),
+        let message = self.message();
+        match self.span() {
+            Some(span) => write!(f, "[line {}] {}", span, message)?,
+            None => write!(f, "{}", message)?,
+        }
+        for frame in &self.trace {
+            write!(f, "\n{}", frame)?;
+        }
+        Ok(())
+    }
+}
+
+/// One entry in the stack trace of a [`RuntimeError`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceFrame {
+    /// The function being executed, or `None` for the top-level script.
+    pub function: Option<String>,
+    /// The file the function, or script, was defined in.
+    pub file: String,
+    /// The line being executed in this frame.
+    pub line: usize,
+}
+
+impl fmt::Display for TraceFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.function {
+            Some(function) => write!(f, "[line {}] in {}() ({})", self.line, function, self.file),
+            None => write!(f, "[line {}] in script ({})", self.line, self.file),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(source: &str) -> Interpreter {
+        let mut interpreter = Interpreter::new();
+        interpreter.run(source).unwrap();
+        interpreter
+    }
+
+    fn global(interpreter: &Interpreter, name: &str) -> Value {
+        interpreter.environment.borrow().get_at(0, name).unwrap()
+    }
+
+    #[test]
+    fn test_closures_and_return() {
+        let interpreter = run(r#"
+            fun makeCounter() {
+                var i = 0;
+                fun count() {
+                    i = i + 1;
+                    return i;
+                }
+                return count;
+            }
+            var counter = makeCounter();
+            counter();
+            var result = counter();
+        "#);
+        assert_eq!(Value::Number(2.0), global(&interpreter, "result"));
+    }
+
+    #[test]
+    fn test_class_initializer_and_methods() {
+        let interpreter = run(r#"
+            class Point {
+                init(x, y) {
+                    this.x = x;
+                    this.y = y;
+                }
+                sum() {
+                    return this.x + this.y;
+                }
+            }
+            var point = Point(1, 2);
+            var sum = point.sum;
+            point.x = 10;
+            var result = sum();
+            var reinit = point.init(0, 0) == point;
+        "#);
+        assert_eq!(Value::Number(12.0), global(&interpreter, "result"));
+        assert_eq!(Value::Boolean(true), global(&interpreter, "reinit"));
+    }
+
+    #[test]
+    fn test_inheritance_and_super() {
+        let interpreter = run(r#"
+            class A {
+                name() { return "A"; }
+                greet() { return "I am " + this.name(); }
+            }
+            class B < A {
+                name() { return "B, child of " + super.name(); }
+            }
+            class C < B {}
+            var result = C().greet();
+        "#);
+        assert_eq!(
+            Value::String("I am B, child of A".into()),
+            global(&interpreter, "result")
+        );
+    }
+
+    #[test]
+    fn test_closure_ignores_later_shadowing() {
+        let interpreter = run(r#"
+            var a = "global";
+            var first;
+            var second;
+            {
+                fun show() {
+                    return a;
+                }
+                first = show();
+                var a = "block";
+                second = show();
+            }
+        "#);
+        assert_eq!(
+            Value::String("global".into()),
+            global(&interpreter, "first")
+        );
+        assert_eq!(
+            Value::String("global".into()),
+            global(&interpreter, "second")
+        );
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let mut interpreter = Interpreter::new();
+        let compile_err = interpreter.run("print 1 +;").unwrap_err();
+        assert_eq!(exitcode::DATAERR, compile_err.exit_code());
+        let runtime_err = interpreter.run("print 1 + nil;").unwrap_err();
+        assert_eq!(exitcode::SOFTWARE, runtime_err.exit_code());
+        let io_err = interpreter.run_file("does/not/exist.lox").unwrap_err();
+        assert_eq!(exitcode::NOINPUT, io_err.exit_code());
+        let dir = std::env::temp_dir();
+        let dir_err = interpreter.run_file(dir.to_str().unwrap()).unwrap_err();
+        assert_eq!(exitcode::NOINPUT, dir_err.exit_code());
+    }
+
+    #[test]
+    fn test_arity_mismatch() {
+        let mut interpreter = Interpreter::new();
+        let err = interpreter.run("fun f(a, b) {} f(1);").unwrap_err();
+        assert!(matches!(
+            err,
+            LoxError::Runtime(err) if matches!(*err.kind, ErrorKind::Arity(_, 2, 1))
+        ));
+    }
+
+    #[test]
+    fn test_errors_render_against_their_source() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .run_source("fun boom() {\n  return 1 + nil;\n}", "lib.lox")
+            .unwrap();
+        let err = interpreter.run_source("boom();", "<stdin>").unwrap_err();
+        let LoxError::Runtime(runtime) = &err else {
+            panic!("expected a runtime error");
         };
-        write!(f, "{}", message)
+        let trace: Vec<_> = runtime
+            .trace
+            .iter()
+            .map(|frame| frame.to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "[line 2] in boom() (lib.lox)",
+                "[line 1] in script (<stdin>)"
+            ],
+            trace
+        );
+        let rendered = interpreter.render(&err, false);
+        assert!(rendered.contains(" --> lib.lox:2:12\n"), "{}", rendered);
+        assert!(rendered.contains("2 |   return 1 + nil;\n"), "{}", rendered);
+    }
+
+    #[test]
+    fn test_stack_overflow() {
+        let code = std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(|| {
+                let mut interpreter = Interpreter::new();
+                let err = interpreter.run("fun f() { f(); } f();").unwrap_err();
+                assert!(interpreter.frames.is_empty());
+                err.exit_code()
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(exitcode::SOFTWARE, code);
+    }
+
+    #[test]
+    fn test_stack_trace() {
+        let mut interpreter = Interpreter::new();
+        let err = interpreter
+            .run("fun inner() {\n  return -nil;\n}\nfun outer() {\n  inner();\n}\nouter();")
+            .unwrap_err();
+        let LoxError::Runtime(err) = err else {
+            panic!("expected a runtime error");
+        };
+        let trace: Vec<_> = err.trace.iter().map(|frame| frame.to_string()).collect();
+        assert_eq!(
+            vec![
+                "[line 2] in inner() (<script>)",
+                "[line 5] in outer() (<script>)",
+                "[line 7] in script (<script>)"
+            ],
+            trace
+        );
+        assert!(interpreter.frames.is_empty());
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        let mut interpreter = Interpreter::new();
+        let message = |interpreter: &mut Interpreter, source| match interpreter.run(source) {
+            Err(LoxError::Runtime(err)) => err.message(),
+            result => panic!("expected a runtime error, got {:?}", result),
+        };
+        assert_eq!(
+            "cannot subtract string from number",
+            message(&mut interpreter, "1 - \"a\";")
+        );
+        assert_eq!("cannot negate nil", message(&mut interpreter, "-nil;"));
+        assert_eq!(
+            "cannot compare boolean with number",
+            message(&mut interpreter, "true < 1;")
+        );
+        let err = interpreter.run("1 / 0;").unwrap_err();
+        assert!(matches!(
+            err,
+            LoxError::Runtime(err) if matches!(*err.kind, ErrorKind::DivisionByZero(_))
+        ));
+    }
+
+    #[test]
+    fn test_try_catch_finally() {
+        let interpreter = run(r#"
+            var a = "outer";
+            var message;
+            var line;
+            var cleanedUp = false;
+            fun fail() {
+                var a = "function";
+                {
+                    var a = "block";
+                    return 1 / 0;
+                }
+            }
+            try {
+                var a = "try";
+                fail();
+            } catch (e) {
+                message = e.message;
+                line = e.line;
+            } finally {
+                cleanedUp = true;
+            }
+            var thrown;
+            try {
+                try {
+                    throw "oops";
+                } finally {
+                    a = a + "!";
+                }
+            } catch (e) {
+                thrown = e;
+            }
+        "#);
+        assert_eq!(
+            Value::String("cannot divide by zero".into()),
+            global(&interpreter, "message")
+        );
+        assert_eq!(Value::Number(10.0), global(&interpreter, "line"));
+        assert_eq!(Value::Boolean(true), global(&interpreter, "cleanedUp"));
+        assert_eq!(Value::String("oops".into()), global(&interpreter, "thrown"));
+        // Unwinding restores the global scope.
+        assert_eq!(Value::String("outer!".into()), global(&interpreter, "a"));
+        assert!(Rc::ptr_eq(&interpreter.environment, &interpreter.globals));
+    }
+
+    #[test]
+    fn test_uncaught_throw() {
+        let mut interpreter = Interpreter::new();
+        let err = interpreter.run("throw \"oops\";").unwrap_err();
+        assert_eq!(
+            "[line 1:1] Uncaught exception: oops\n[line 1] in script (<script>)",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_repl_errors_restore_global_scope() {
+        // Each line of a REPL session is run by the same interpreter.
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .run("{ var a = \"block\"; { var b = a; print b + 1; } }")
+            .unwrap_err();
+        interpreter.run("var a = \"global\";").unwrap();
+        interpreter
+            .run("fun f() { var a = \"local\"; { nil(); } } f();")
+            .unwrap_err();
+        interpreter.run("var b = a;").unwrap();
+        assert!(Rc::ptr_eq(&interpreter.environment, &interpreter.globals));
+        assert_eq!(Value::String("global".into()), global(&interpreter, "b"));
+    }
+
+    #[test]
+    fn test_break_and_continue() {
+        let interpreter = run(r#"
+            var sum = 0;
+            for (var i = 0; i < 10; i = i + 1) {
+                var skip = i == 2;
+                if (skip) { continue; }
+                if (i == 5) { break; }
+                sum = sum + i;
+            }
+            var pairs = "";
+            outer: for (var i = 0; i < 3; i = i + 1) {
+                var j = 0;
+                while (true) {
+                    j = j + 1;
+                    if (j > i) { continue outer; }
+                    if (i == 2) { break outer; }
+                    pairs = pairs + "x";
+                }
+            }
+        "#);
+        assert_eq!(Value::Number(8.0), global(&interpreter, "sum"));
+        assert_eq!(Value::String("x".into()), global(&interpreter, "pairs"));
+        assert!(Rc::ptr_eq(&interpreter.environment, &interpreter.globals));
+    }
+
+    #[test]
+    fn test_repl_mode() {
+        let mut interpreter = Interpreter::new().with_repl(true);
+        interpreter.run("var a = 1; a = a + 2").unwrap();
+        assert_eq!(Value::Number(3.0), global(&interpreter, "a"));
+        let mut interpreter = Interpreter::new();
+        assert!(interpreter.run("1 + 2").is_err());
+
+        // Files loaded from the REPL are still parsed strictly.
+        let path = std::env::temp_dir().join("rlox_test_repl_mode.lox");
+        fs::write(&path, "var x = 1;\nx").unwrap();
+        let mut interpreter = Interpreter::new().with_repl(true);
+        let result = interpreter.run_file(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(LoxError::Compile(_))));
+        interpreter.run("1 + 2").unwrap();
+    }
+
+    #[test]
+    fn test_script_args() {
+        std::env::set_var("RLOX_TEST_SCRIPT_ARGS", "set");
+        std::env::remove_var("RLOX_TEST_UNSET");
+        let mut interpreter = Interpreter::new().with_args(vec!["a".into(), "b".into()]);
+        interpreter
+            .run(
+                "var n = args.length; var first = args[0];
+                 var set = env(\"RLOX_TEST_SCRIPT_ARGS\"); var unset = env(\"RLOX_TEST_UNSET\");",
+            )
+            .unwrap();
+        assert_eq!(Value::Number(2.0), global(&interpreter, "n"));
+        assert_eq!(Value::String("a".into()), global(&interpreter, "first"));
+        assert_eq!(Value::String("set".into()), global(&interpreter, "set"));
+        assert_eq!(Value::Nil, global(&interpreter, "unset"));
+        assert!(interpreter.run("args[2];").is_err());
+        assert!(interpreter.run("env(1);").is_err());
+
+        let err = interpreter.run("print 1; exit(3); print 2;").unwrap_err();
+        assert_eq!(3, err.exit_code());
+        // `exit` cannot be caught.
+        let err = interpreter
+            .run("try { exit(4); } catch (e) {}")
+            .unwrap_err();
+        assert_eq!(4, err.exit_code());
     }
 }
@@ -1,29 +1,48 @@
 #[macro_use]
 extern crate lazy_static;
 
-use std::{env, error::Error, process::exit};
+use std::{env, process::exit, thread};
 
-use interpreter::Interpreter;
+use cli::Command;
+use interpreter::LoxError;
 
+mod callable;
+mod class;
+mod cli;
+mod diagnostics;
 mod environment;
 mod expr;
 mod interpreter;
 mod parser;
+mod repl;
+mod resolver;
 mod scanner;
 mod stmt;
 mod token;
+mod value;
 
-pub type Result<T> = std::result::Result<T, Box<dyn Error>>;
-
-fn main() -> Result<()> {
+fn main() {
     let args: Vec<String> = env::args().skip(1).collect();
-    let mut interpreter = Interpreter::new();
-    match args.len() {
-        2.. => {
-            eprintln!("Usage: rlox [script]");
-            exit(exitcode::USAGE);
-        }
-        1 => interpreter.run_file(&args[0]),
-        _ => interpreter.run_prompt(),
-    }
+    let command = Command::parse(&args).unwrap_or_else(|message| {
+        eprintln!("error: {}\n\n{}", message, cli::USAGE);
+        exit(exitcode::USAGE);
+    });
+    // Each Lox call takes several nested Rust calls, so scripts run on a
+    // thread with a larger stack than the main thread's.
+    let code = thread::Builder::new()
+        .stack_size(interpreter::STACK_SIZE)
+        .spawn(|| match command.execute() {
+            Ok(()) => exitcode::OK,
+            Err(err) => {
+                // Every other error is reported where it is raised.
+                if let LoxError::Io(_) = err {
+                    eprintln!("error: {}", err);
+                }
+                err.exit_code()
+            }
+        })
+        .expect("failed to spawn the interpreter thread")
+        .join()
+        .unwrap_or(exitcode::SOFTWARE);
+    exit(code);
 }
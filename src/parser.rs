@@ -1,38 +1,150 @@
 use std::{error, fmt};
 
-use crate::stmt::Stmt;
-use crate::Result;
-use crate::{expr::*, token::*};
+use std::rc::Rc;
+
+use crate::scanner::ScannerError;
+use crate::stmt::{FunctionDecl, Stmt};
+use crate::{expr::*, token::*, value::Value};
+
+type Result<T> = std::result::Result<T, ParseError>;
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    errors: CompileErrors,
+    /// The labels of the loops enclosing the current statement, innermost
+    /// last, within the current function body.
+    loops: Vec<Option<Token>>,
+    /// Whether the source was entered at the REPL, where a trailing
+    /// expression may omit its `;` and is printed.
+    repl: bool,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, current: 0 }
+    /// Creates a parser for the output of the scanner. Lexical errors are
+    /// reported alongside any syntax errors found when parsing.
+    pub fn new(tokens: Vec<Token>, scanner_errors: Vec<ScannerError>) -> Self {
+        Parser {
+            tokens,
+            current: 0,
+            errors: CompileErrors {
+                scanner: scanner_errors,
+                parser: Vec::new(),
+            },
+            loops: Vec::new(),
+            repl: false,
+        }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Stmt>> {
+    /// Enables or disables REPL mode.
+    pub fn with_repl(self, repl: bool) -> Self {
+        Parser { repl, ..self }
+    }
+
+    /// Parses the whole program, recovering from syntax errors so that every
+    /// error in the source is reported at once.
+    pub fn parse(&mut self) -> std::result::Result<Vec<Stmt>, CompileErrors> {
         let mut statements = Vec::new();
         while !self.is_at_end() {
-            statements.push(self.declaration()?);
+            if let Some(stmt) = self.declaration() {
+                statements.push(stmt);
+            }
+        }
+        if self.errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(std::mem::take(&mut self.errors))
         }
-        Ok(statements)
     }
 
-    fn declaration(&mut self) -> Result<Stmt> {
-        if self.matches_token(TokenType::Var) {
-            self.var_declaration().or_else(|_| {
+    /// Parses a declaration, or records the error and skips to the start of
+    /// the next statement if it is malformed.
+    fn declaration(&mut self) -> Option<Stmt> {
+        let result = if self.matches_token(TokenType::Class) {
+            self.class_declaration()
+        } else if self.matches_token(TokenType::Fun) {
+            self.function(FunctionKind::Function).map(Stmt::Function)
+        } else if self.matches_token(TokenType::Var) {
+            self.var_declaration()
+        } else {
+            self.statement()
+        };
+        match result {
+            Ok(stmt) => Some(stmt),
+            Err(err) => {
+                self.error(err);
                 self.synchronize();
-                Ok(Stmt::Expression(Expr::Literal(LiteralExpr {
-                    value: Literal::Nil,
-                })))
+                None
+            }
+        }
+    }
+
+    /// Records an error. Errors at error tokens are dropped since the scanner
+    /// has already reported them.
+    fn error(&mut self, err: ParseError) {
+        if err.token.typ != TokenType::Error {
+            self.errors.parser.push(err);
+        }
+    }
+
+    fn class_declaration(&mut self) -> Result<Stmt> {
+        let name = self.consume(TokenType::Identifier, "Expected class name.")?;
+        let superclass = if self.matches_token(TokenType::Less) {
+            let superclass = self.consume(TokenType::Identifier, "Expected superclass name.")?;
+            if superclass.lexeme == name.lexeme {
+                return Err(ParseError::new(
+                    superclass,
+                    "A class can't inherit from itself.",
+                ));
+            }
+            Some(VariableExpr {
+                id: ExprId::fresh(),
+                name: superclass,
             })
         } else {
-            self.statement()
+            None
+        };
+        self.consume(TokenType::LeftBrace, "Expected '{' before class body.")?;
+
+        let mut methods = Vec::new();
+        while !self.check_token(TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(self.function(FunctionKind::Method)?);
         }
+        self.consume(TokenType::RightBrace, "Expected '}' after class body.")?;
+
+        Ok(Stmt::Class(name, superclass, methods))
+    }
+
+    fn function(&mut self, kind: FunctionKind) -> Result<Rc<FunctionDecl>> {
+        let name = self.consume(TokenType::Identifier, kind.name_message())?;
+        self.consume(TokenType::LeftParen, kind.paren_message())?;
+        let mut params = Vec::new();
+        if !self.check_token(TokenType::RightParen) {
+            loop {
+                if params.len() >= 255 {
+                    self.error(ParseError::new(
+                        self.peek().clone(),
+                        "Can't have more than 255 parameters.",
+                    ));
+                }
+                params.push(self.consume(TokenType::Identifier, "Expected parameter name.")?);
+                if !self.matches_token(TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expected ')' after parameters.")?;
+
+        self.consume(TokenType::LeftBrace, "Expected '{' before function body.")?;
+        // Loops outside the function can't be broken out of from inside it.
+        let loops = std::mem::take(&mut self.loops);
+        let body = self.block_statements();
+        self.loops = loops;
+        Ok(Rc::new(FunctionDecl {
+            name,
+            params,
+            body: body?,
+        }))
     }
 
     fn var_declaration(&mut self) -> Result<Stmt> {
@@ -50,8 +162,26 @@ impl Parser {
     }
 
     fn statement(&mut self) -> Result<Stmt> {
-        if self.matches_token(TokenType::Print) {
+        if self.check_token(TokenType::Identifier) && self.check_next(TokenType::Colon) {
+            self.labeled_statement()
+        } else if self.matches_token(TokenType::For) {
+            self.for_statement(None)
+        } else if self.matches_token(TokenType::Break) {
+            self.loop_control_statement(Stmt::Break)
+        } else if self.matches_token(TokenType::Continue) {
+            self.loop_control_statement(Stmt::Continue)
+        } else if self.matches_token(TokenType::If) {
+            self.if_statement()
+        } else if self.matches_token(TokenType::Print) {
             self.print_statement()
+        } else if self.matches_token(TokenType::Return) {
+            self.return_statement()
+        } else if self.matches_token(TokenType::Throw) {
+            self.throw_statement()
+        } else if self.matches_token(TokenType::Try) {
+            self.try_statement()
+        } else if self.matches_token(TokenType::While) {
+            self.while_statement(None)
         } else if self.matches_token(TokenType::LeftBrace) {
             self.block()
         } else {
@@ -59,13 +189,190 @@ impl Parser {
         }
     }
 
+    /// Parses a loop preceded by a label, e.g. `outer: while (true) { ... }`.
+    fn labeled_statement(&mut self) -> Result<Stmt> {
+        let label = self.advance();
+        self.advance();
+        if self.matches_token(TokenType::For) {
+            self.for_statement(Some(label))
+        } else if self.matches_token(TokenType::While) {
+            self.while_statement(Some(label))
+        } else {
+            Err(ParseError::new(
+                self.peek().clone(),
+                "Expected a loop after label.",
+            ))
+        }
+    }
+
+    /// Parses a `break` or `continue` statement, checking that it is inside
+    /// a loop with the given label, if any.
+    fn loop_control_statement(&mut self, make: fn(Token, Option<Token>) -> Stmt) -> Result<Stmt> {
+        let keyword = self.previous();
+        let label = if self.check_token(TokenType::Identifier) {
+            Some(self.advance())
+        } else {
+            None
+        };
+        self.consume(
+            TokenType::Semicolon,
+            format!("Expected ';' after '{}'.", keyword.lexeme),
+        )?;
+
+        match &label {
+            _ if self.loops.is_empty() => self.error(ParseError::new(
+                keyword.clone(),
+                format!("Can't use '{}' outside of a loop.", keyword.lexeme),
+            )),
+            Some(label)
+                if !self
+                    .loops
+                    .iter()
+                    .flatten()
+                    .any(|l| l.lexeme == label.lexeme) =>
+            {
+                self.error(ParseError::new(
+                    label.clone(),
+                    format!("No enclosing loop labeled '{}'.", label.lexeme),
+                ))
+            }
+            _ => {}
+        }
+        Ok(make(keyword, label))
+    }
+
+    /// Parses the body of a loop with the given label.
+    fn loop_body(&mut self, label: Option<Token>) -> Result<Stmt> {
+        self.loops.push(label);
+        let body = self.statement();
+        self.loops.pop();
+        body
+    }
+
+    /// Parses a `for` loop, desugaring it into an equivalent `while` loop
+    /// wrapped in a block which scopes the initializer.
+    fn for_statement(&mut self, label: Option<Token>) -> Result<Stmt> {
+        let keyword = self.previous();
+        self.consume(TokenType::LeftParen, "Expected '(' after 'for'.")?;
+        let initializer = if self.matches_token(TokenType::Semicolon) {
+            None
+        } else if self.matches_token(TokenType::Var) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let condition = if self.check_token(TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(TokenType::Semicolon, "Expected ';' after loop condition.")?;
+
+        let increment = if self.check_token(TokenType::RightParen) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(TokenType::RightParen, "Expected ')' after for clauses.")?;
+
+        let body = self.loop_body(label.clone())?;
+        let condition = condition.unwrap_or(Expr::Literal(LiteralExpr {
+            value: Value::Boolean(true),
+            span: keyword.span,
+        }));
+        let mut body = Stmt::While(condition, Box::new(body), increment.map(Box::new), label);
+        if let Some(initializer) = initializer {
+            body = Stmt::Block(vec![initializer, body]);
+        }
+
+        Ok(body)
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt> {
+        self.consume(TokenType::LeftParen, "Expected '(' after 'if'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "Expected ')' after if condition.")?;
+
+        let then_branch = Box::new(self.statement()?);
+        let else_branch = if self.matches_token(TokenType::Else) {
+            Some(Box::new(self.statement()?))
+        } else {
+            None
+        };
+        Ok(Stmt::If(condition, then_branch, else_branch))
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.previous();
+        let value = if self.check_token(TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(TokenType::Semicolon, "Expected ';' after return value.")?;
+        Ok(Stmt::Return(keyword, value))
+    }
+
+    fn throw_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.previous();
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expected ';' after thrown value.")?;
+        Ok(Stmt::Throw(keyword, value))
+    }
+
+    fn try_statement(&mut self) -> Result<Stmt> {
+        self.consume(TokenType::LeftBrace, "Expected '{' after 'try'.")?;
+        let body = self.block_statements()?;
+
+        let catch = if self.matches_token(TokenType::Catch) {
+            self.consume(TokenType::LeftParen, "Expected '(' after 'catch'.")?;
+            let name = self.consume(TokenType::Identifier, "Expected error variable name.")?;
+            self.consume(TokenType::RightParen, "Expected ')' after error variable.")?;
+            self.consume(TokenType::LeftBrace, "Expected '{' before catch body.")?;
+            Some((name, self.block_statements()?))
+        } else {
+            None
+        };
+
+        let finally = if self.matches_token(TokenType::Finally) {
+            self.consume(TokenType::LeftBrace, "Expected '{' after 'finally'.")?;
+            Some(self.block_statements()?)
+        } else {
+            None
+        };
+
+        if catch.is_none() && finally.is_none() {
+            return Err(ParseError::new(
+                self.peek().clone(),
+                "Expected 'catch' or 'finally' after try block.",
+            ));
+        }
+        Ok(Stmt::Try(body, catch, finally))
+    }
+
+    fn while_statement(&mut self, label: Option<Token>) -> Result<Stmt> {
+        self.consume(TokenType::LeftParen, "Expected '(' after 'while'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "Expected ')' after condition.")?;
+        let body = self.loop_body(label.clone())?;
+        Ok(Stmt::While(condition, Box::new(body), None, label))
+    }
+
     fn block(&mut self) -> Result<Stmt> {
+        Ok(Stmt::Block(self.block_statements()?))
+    }
+
+    /// Parses the statements of a block up to and including the closing `}`.
+    fn block_statements(&mut self) -> Result<Vec<Stmt>> {
         let mut statements = Vec::new();
         while !self.check_token(TokenType::RightBrace) && !self.is_at_end() {
-            statements.push(self.declaration()?);
+            if let Some(stmt) = self.declaration() {
+                statements.push(stmt);
+            }
         }
         self.consume(TokenType::RightBrace, "Expected '}' after block.")?;
-        Ok(Stmt::Block(statements))
+        Ok(statements)
     }
 
     fn print_statement(&mut self) -> Result<Stmt> {
@@ -76,7 +383,15 @@ impl Parser {
 
     fn expression_statement(&mut self) -> Result<Stmt> {
         let expr = self.expression()?;
+        // In the REPL, the value of the final expression is echoed, whether
+        // or not it ends in a `;`.
+        if self.repl && self.is_at_end() {
+            return Ok(Stmt::Print(expr));
+        }
         self.consume(TokenType::Semicolon, "Expected ';' after expression.")?;
+        if self.repl && self.is_at_end() {
+            return Ok(Stmt::Print(expr));
+        }
         Ok(Stmt::Expression(expr))
     }
 
@@ -85,18 +400,63 @@ impl Parser {
     }
 
     fn assignment(&mut self) -> Result<Expr> {
-        let expr = self.equality()?;
-        if self.check_token(TokenType::Equal) {
-            let equals = self.advance();
-            let value = self.assignment()?;
-            if let Expr::Variable(var) = expr {
-                return Ok(Expr::Assign(AssignExpr {
-                    name: var.name,
-                    value: Box::new(value),
-                }));
+        let expr = self.or()?;
+        if !self.check_token(TokenType::Equal) {
+            return Ok(expr);
+        }
+
+        let equals = self.advance();
+        let value = self.assignment()?;
+        match expr {
+            Expr::Variable(var) => Ok(Expr::Assign(AssignExpr {
+                id: ExprId::fresh(),
+                name: var.name,
+                value: Box::new(value),
+            })),
+            Expr::Get(get) => Ok(Expr::Set(SetExpr {
+                object: get.object,
+                name: get.name,
+                value: Box::new(value),
+            })),
+            // The parser isn't confused, so there's no need to synchronize.
+            expr => {
+                self.error(
+                    ParseError::new(equals, "Invalid assignment target.").with_span(expr.span()),
+                );
+                Ok(expr)
             }
-            eprintln!("{}", ParseError::new(equals, "Invalid assignment target"));
         }
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+
+        while self.check_token(TokenType::Or) {
+            let operator = self.advance();
+            let right = self.and()?;
+            expr = Expr::Logical(LogicalExpr {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            })
+        }
+
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.equality()?;
+
+        while self.check_token(TokenType::And) {
+            let operator = self.advance();
+            let right = self.equality()?;
+            expr = Expr::Logical(LogicalExpr {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            })
+        }
+
         Ok(expr)
     }
 
@@ -177,29 +537,108 @@ impl Parser {
                 right: Box::new(right),
             }))
         } else {
-            self.primary()
+            self.call()
+        }
+    }
+
+    fn call(&mut self) -> Result<Expr> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.matches_token(TokenType::LeftParen) {
+                expr = self.finish_call(expr)?;
+            } else if self.matches_token(TokenType::Dot) {
+                let name =
+                    self.consume(TokenType::Identifier, "Expected property name after '.'.")?;
+                expr = Expr::Get(GetExpr {
+                    object: Box::new(expr),
+                    name,
+                });
+            } else if self.matches_token(TokenType::LeftBracket) {
+                let index = self.expression()?;
+                let bracket = self.consume(TokenType::RightBracket, "Expected ']' after index.")?;
+                expr = Expr::Index(IndexExpr {
+                    object: Box::new(expr),
+                    bracket,
+                    index: Box::new(index),
+                });
+            } else {
+                break;
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr> {
+        let mut arguments = Vec::new();
+        if !self.check_token(TokenType::RightParen) {
+            loop {
+                if arguments.len() >= 255 {
+                    self.error(ParseError::new(
+                        self.peek().clone(),
+                        "Can't have more than 255 arguments.",
+                    ));
+                }
+                arguments.push(self.expression()?);
+                if !self.matches_token(TokenType::Comma) {
+                    break;
+                }
+            }
         }
+        let paren = self.consume(TokenType::RightParen, "Expected ')' after arguments.")?;
+
+        Ok(Expr::Call(CallExpr {
+            callee: Box::new(callee),
+            paren,
+            arguments,
+        }))
     }
 
     fn primary(&mut self) -> Result<Expr> {
-        let token = self.advance();
-        let expr = match token.typ {
+        // The token is only consumed once it is known to start an expression,
+        // so that `synchronize` begins its search at the offending token.
+        let expr = match self.peek().typ {
             TokenType::LeftParen => {
+                let open = self.advance();
                 let expr = self.expression()?;
-                self.consume(TokenType::RightParen, "expected ')' after expression")?;
+                let paren = self.consume(TokenType::RightParen, "expected ')' after expression")?;
                 Expr::Grouping(GroupingExpr {
                     expression: Box::new(expr),
+                    span: open.span.to(paren.span),
                 })
             }
             TokenType::String
             | TokenType::Number
             | TokenType::True
             | TokenType::False
-            | TokenType::Nil => Expr::Literal(LiteralExpr {
-                value: token.literal.unwrap_or(Literal::Nil),
+            | TokenType::Nil => {
+                let token = self.advance();
+                Expr::Literal(LiteralExpr {
+                    value: token.literal.map_or(Value::Nil, Value::from),
+                    span: token.span,
+                })
+            }
+            TokenType::This => Expr::This(ThisExpr {
+                id: ExprId::fresh(),
+                keyword: self.advance(),
+            }),
+            TokenType::Super => {
+                let keyword = self.advance();
+                self.consume(TokenType::Dot, "Expected '.' after 'super'.")?;
+                let method =
+                    self.consume(TokenType::Identifier, "Expected superclass method name.")?;
+                Expr::Super(SuperExpr {
+                    id: ExprId::fresh(),
+                    keyword,
+                    method,
+                })
+            }
+            TokenType::Identifier => Expr::Variable(VariableExpr {
+                id: ExprId::fresh(),
+                name: self.advance(),
             }),
-            TokenType::Identifier => Expr::Variable(VariableExpr { name: token }),
-            _ => return Err(ParseError::new(token, "expected expression").into()),
+            _ => return Err(ParseError::new(self.peek().clone(), "expected expression")),
         };
         Ok(expr)
     }
@@ -208,6 +647,13 @@ impl Parser {
         &self.tokens[self.current]
     }
 
+    /// Checks the type of the token after the current one.
+    fn check_next(&self, typ: TokenType) -> bool {
+        self.tokens
+            .get(self.current + 1)
+            .is_some_and(|token| token.typ == typ)
+    }
+
     fn check_token(&self, typ: TokenType) -> bool {
         if self.is_at_end() {
             false
@@ -232,11 +678,11 @@ impl Parser {
         self.previous()
     }
 
-    fn consume(&mut self, typ: TokenType, msg: &'static str) -> Result<Token> {
+    fn consume(&mut self, typ: TokenType, msg: impl Into<String>) -> Result<Token> {
         if self.check_token(typ) {
             Ok(self.advance())
         } else {
-            Err(ParseError::new(self.peek().clone(), msg).into())
+            Err(ParseError::new(self.peek().clone(), msg))
         }
     }
 
@@ -264,7 +710,11 @@ impl Parser {
                 | TokenType::If
                 | TokenType::While
                 | TokenType::Print
-                | TokenType::Return => {
+                | TokenType::Return
+                | TokenType::Break
+                | TokenType::Continue
+                | TokenType::Throw
+                | TokenType::Try => {
                     return;
                 }
                 _ => {}
@@ -275,15 +725,49 @@ impl Parser {
     }
 }
 
+/// The kinds of function-like declarations, used to tailor error messages.
+#[derive(Debug, Clone, Copy)]
+enum FunctionKind {
+    Function,
+    Method,
+}
+
+impl FunctionKind {
+    fn name_message(self) -> &'static str {
+        match self {
+            FunctionKind::Function => "Expected function name.",
+            FunctionKind::Method => "Expected method name.",
+        }
+    }
+
+    fn paren_message(self) -> &'static str {
+        match self {
+            FunctionKind::Function => "Expected '(' after function name.",
+            FunctionKind::Method => "Expected '(' after method name.",
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ParseError {
-    pub token: Token,
-    pub message: &'static str,
+    pub token: Box<Token>,
+    pub message: String,
+    /// The region of source the error applies to, which defaults to the
+    /// token's span.
+    pub span: Span,
 }
 
 impl ParseError {
-    pub fn new(token: Token, message: &'static str) -> Self {
-        ParseError { token, message }
+    pub fn new(token: Token, message: impl Into<String>) -> Self {
+        ParseError {
+            span: token.span,
+            token: Box::new(token),
+            message: message.into(),
+        }
+    }
+
+    pub fn with_span(self, span: Span) -> Self {
+        ParseError { span, ..self }
     }
 }
 
@@ -292,13 +776,177 @@ impl error::Error for ParseError {}
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if self.token.typ == TokenType::EOF {
-            write!(f, "{} at end: {}", self.token.line, self.message)
+            write!(f, "[line {}] Error at end: {}", self.span, self.message)
         } else {
             write!(
                 f,
-                "{} at {}: {}",
-                self.token.line, self.token.lexeme, self.message
+                "[line {}] Error at '{}': {}",
+                self.span, self.token.lexeme, self.message
             )
         }
     }
 }
+
+/// Every error found in a program before it is run.
+#[derive(Debug, Default)]
+pub struct CompileErrors {
+    pub scanner: Vec<ScannerError>,
+    /// Syntax errors, along with any static errors found by the resolver.
+    pub parser: Vec<ParseError>,
+}
+
+impl CompileErrors {
+    pub fn is_empty(&self) -> bool {
+        self.scanner.is_empty() && self.parser.is_empty()
+    }
+}
+
+impl error::Error for CompileErrors {}
+
+impl fmt::Display for CompileErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let scanner = self.scanner.iter().map(|err| err as &dyn fmt::Display);
+        let parser = self.parser.iter().map(|err| err as &dyn fmt::Display);
+        for (i, error) in scanner.chain(parser).enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        let (tokens, errors) = Scanner::new(source).scan_tokens();
+        Parser::new(tokens, errors).parse().unwrap()
+    }
+
+    #[test]
+    fn test_for_desugars_to_while() {
+        let stmts = parse("for (var i = 0; i < 3; i = i + 1) print i;");
+        assert_eq!(stmts.len(), 1);
+        let Stmt::Block(outer) = &stmts[0] else {
+            panic!("expected block, got {:?}", stmts[0]);
+        };
+        assert!(matches!(outer[0], Stmt::Var(..)));
+        let Stmt::While(condition, body, Some(increment), None) = &outer[1] else {
+            panic!("expected while, got {:?}", outer[1]);
+        };
+        assert_eq!("(< i 3)", format!("{}", condition));
+        assert_eq!("(i (+ i 1))", format!("{}", increment));
+        assert!(matches!(body.as_ref(), Stmt::Print(_)));
+    }
+
+    #[test]
+    fn test_expression_spans() {
+        let stmts = parse("var x = 1;\nprint (x + 2) * foo.bar(3);");
+        let Stmt::Print(expr) = &stmts[1] else {
+            panic!("expected print, got {:?}", stmts[1]);
+        };
+        assert_eq!(Span::new(17, 37, 2, 7), expr.span());
+    }
+
+    #[test]
+    fn test_reports_every_syntax_error() {
+        let source = "var = 1;\nprint 1 +;\n{ var ok = 1; }\nfun (a) {}\nprint \"done\";";
+        let (tokens, errors) = Scanner::new(source).scan_tokens();
+        let errors = Parser::new(tokens, errors).parse().unwrap_err();
+        let errors: Vec<_> = errors
+            .parser
+            .iter()
+            .map(|err| (err.token.span.line, err.message.as_str()))
+            .collect();
+        assert_eq!(
+            vec![
+                (1, "Expected variable name."),
+                (2, "expected expression"),
+                (4, "Expected function name."),
+            ],
+            errors
+        );
+    }
+
+    #[test]
+    fn test_reports_consecutive_expression_errors() {
+        let source = "print 1 +;\nprint 2 +;\nprint 3 +;\n1 + ;\nprint 4;";
+        let (tokens, errors) = Scanner::new(source).scan_tokens();
+        let errors = Parser::new(tokens, errors).parse().unwrap_err();
+        let lines: Vec<_> = errors
+            .parser
+            .iter()
+            .map(|err| err.token.span.line)
+            .collect();
+        assert_eq!(vec![1, 2, 3, 4], lines);
+    }
+
+    #[test]
+    fn test_skips_errors_at_error_tokens() {
+        let (tokens, errors) = Scanner::new("print 1 + @;\nprint 2 +;").scan_tokens();
+        let errors = Parser::new(tokens, errors).parse().unwrap_err();
+        assert_eq!(1, errors.scanner.len());
+        assert_eq!(1, errors.parser.len());
+        assert_eq!(2, errors.parser[0].token.span.line);
+    }
+
+    #[test]
+    fn test_invalid_assignment_target() {
+        let (tokens, errors) = Scanner::new("var a;\n1 + a = 2;\na = 3;").scan_tokens();
+        let errors = Parser::new(tokens, errors).parse().unwrap_err();
+        assert_eq!(1, errors.parser.len());
+        let err = &errors.parser[0];
+        assert_eq!("Invalid assignment target.", err.message);
+        assert_eq!(Span::new(7, 12, 2, 1), err.span);
+    }
+
+    #[test]
+    fn test_logical_precedence() {
+        let stmts = parse("a or b and c == d;");
+        let Stmt::Expression(expr) = &stmts[0] else {
+            panic!("expected expression, got {:?}", stmts[0]);
+        };
+        assert_eq!("(or a (and b (== c d)))", format!("{}", expr));
+    }
+
+    #[test]
+    fn test_loop_control_outside_loop() {
+        let source = "break;
+while (true) { fun f() { continue; } }
+a: while (true) { break b; }";
+        let (tokens, errors) = Scanner::new(source).scan_tokens();
+        let errors = Parser::new(tokens, errors).parse().unwrap_err();
+        let errors: Vec<_> = errors.parser.iter().map(|err| err.to_string()).collect();
+        assert_eq!(
+            vec![
+                "[line 1:1] Error at 'break': Can't use 'break' outside of a loop.",
+                "[line 2:26] Error at 'continue': Can't use 'continue' outside of a loop.",
+                "[line 3:25] Error at 'b': No enclosing loop labeled 'b'.",
+            ],
+            errors
+        );
+    }
+
+    #[test]
+    fn test_repl_trailing_expression() {
+        let parse_repl = |source, repl| {
+            let (tokens, errors) = Scanner::new(source).scan_tokens();
+            Parser::new(tokens, errors).with_repl(repl).parse()
+        };
+        let stmts = parse_repl("var a = 1; a + 2", true).unwrap();
+        assert!(matches!(&stmts[1], Stmt::Print(expr) if expr.to_string() == "(+ a 2)"));
+        let stmts = parse_repl("a + 2; a + 3;", true).unwrap();
+        assert!(matches!(&stmts[0], Stmt::Expression(_)));
+        assert!(matches!(&stmts[1], Stmt::Print(expr) if expr.to_string() == "(+ a 3)"));
+        let stmts = parse_repl("{ a + 2; }", true).unwrap();
+        assert!(matches!(&stmts[0], Stmt::Block(block) if matches!(block[0], Stmt::Expression(_))));
+        assert!(parse_repl("{ a + 2 }", true).is_err());
+        assert!(parse_repl("a + 2", false).is_err());
+        let stmts = parse_repl("a + 2;", false).unwrap();
+        assert!(matches!(&stmts[0], Stmt::Expression(_)));
+    }
+}
@@ -0,0 +1,282 @@
+use std::{env, fmt::Write, io, path::PathBuf, time::Instant};
+
+use rustyline::{
+    completion::Completer,
+    error::ReadlineError,
+    highlight::Highlighter,
+    hint::Hinter,
+    history::FileHistory,
+    validate::{ValidationContext, ValidationResult, Validator},
+    Config, Editor, Helper,
+};
+
+use crate::{
+    interpreter::{self, Interpreter, LoxError},
+    parser::{CompileErrors, Parser},
+    scanner::{Scanner, ScannerErrorKind},
+    stmt::Stmt,
+    token::TokenType,
+};
+
+const PROMPT: &str = "> ";
+const HISTORY_FILE: &str = ".rlox_history";
+
+const HELP: &str = "\
+Enter Lox code to run it. The value of a final expression is printed, and
+its ';' may be left out.
+
+Commands:
+  :help           Show this message
+  :env            List the variables in every scope
+  :ast <code>     Show the syntax tree of <code>
+  :tokens <code>  Show the tokens of <code>
+  :load <file>    Run the script in <file>
+  :reset          Discard all variables and definitions
+  :time <code>    Run <code> and show how long it took
+
+Press Ctrl-C to discard the current input and Ctrl-D to exit.";
+
+/// An interactive session, with line editing, persistent history and
+/// multi-line input.
+pub struct Repl {
+    interpreter: Interpreter,
+    editor: Editor<LoxHelper, FileHistory>,
+    /// Where history is saved between sessions, if a home directory is set.
+    history: Option<PathBuf>,
+}
+
+impl Repl {
+    pub fn new(interpreter: Interpreter) -> Result<Self, LoxError> {
+        let config = Config::builder().auto_add_history(true).build();
+        let mut editor = Editor::with_config(config).map_err(readline_error)?;
+        editor.set_helper(Some(LoxHelper));
+
+        let history = env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+        if let Some(history) = &history {
+            // There is no history yet on the first run.
+            let _ = editor.load_history(history);
+        }
+
+        Ok(Repl {
+            interpreter: interpreter.with_repl(true),
+            editor,
+            history,
+        })
+    }
+
+    /// Reads and runs input until the user exits with Ctrl-D or the script
+    /// calls `exit`. Errors in the input are reported without ending the
+    /// session, and Ctrl-C discards the current input.
+    pub fn run(&mut self) -> Result<(), LoxError> {
+        let result = loop {
+            let result = match self.editor.readline(PROMPT) {
+                Ok(input) => match input.trim_start().strip_prefix(':') {
+                    Some(command) => self.command(command),
+                    None if input.trim().is_empty() => Ok(()),
+                    None => self.interpreter.run_source(&input, "<stdin>"),
+                },
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => break Ok(()),
+                Err(err) => break Err(readline_error(err)),
+            };
+            // Any other error has already been reported.
+            if let Err(LoxError::Exit(code)) = result {
+                break Err(LoxError::Exit(code));
+            }
+        };
+
+        if let Some(history) = &self.history {
+            if let Err(err) = self.editor.save_history(history) {
+                eprintln!(
+                    "warning: could not save history to {}: {}",
+                    history.display(),
+                    err
+                );
+            }
+        }
+        result
+    }
+
+    /// Runs a meta-command, given without its leading `:`. Errors are
+    /// reported, then returned.
+    fn command(&mut self, command: &str) -> Result<(), LoxError> {
+        let command = command.trim();
+        let (name, argument) = command
+            .split_once(char::is_whitespace)
+            .map_or((command, ""), |(name, argument)| (name, argument.trim()));
+        match (name, argument) {
+            ("help", _) => println!("{}", HELP),
+            ("env", _) => print!("{}", list_environment(&self.interpreter)),
+            ("reset", _) => {
+                self.interpreter = Interpreter::new().with_repl(true);
+            }
+            ("load", "") => eprintln!("Usage: :load <file>"),
+            ("ast" | "tokens" | "time", "") => eprintln!("Usage: :{} <code>", name),
+            ("ast", code) => match parse(code) {
+                Ok(stmts) => {
+                    for stmt in stmts {
+                        println!("{}", stmt);
+                    }
+                }
+                Err(errors) => interpreter::report(&errors.into(), code, "<stdin>"),
+            },
+            ("tokens", code) => {
+                let (tokens, errors) = list_tokens(code);
+                print!("{}", tokens);
+                if !errors.is_empty() {
+                    interpreter::report(&errors.into(), code, "<stdin>");
+                }
+            }
+            ("load", file) => return self.interpreter.run_file(file),
+            ("time", code) => {
+                let start = Instant::now();
+                let result = self.interpreter.run_source(code, "<stdin>");
+                println!("Took {:?}", start.elapsed());
+                return result;
+            }
+            _ => eprintln!(
+                "Unknown command ':{}'. Type :help for a list of commands.",
+                name
+            ),
+        }
+        Ok(())
+    }
+}
+
+fn parse(source: &str) -> Result<Vec<Stmt>, CompileErrors> {
+    let (tokens, errors) = Scanner::new(source).scan_tokens();
+    Parser::new(tokens, errors).with_repl(true).parse()
+}
+
+/// Lists the variables in each scope, from the innermost scope out to the
+/// globals.
+fn list_environment(interpreter: &Interpreter) -> String {
+    let mut out = String::new();
+    let mut environment = Some(interpreter.environment());
+    let mut depth = 0;
+    while let Some(current) = environment {
+        let current = current.borrow();
+        if current.enclosing.is_some() {
+            writeln!(out, "scope {}:", depth).unwrap();
+        } else {
+            writeln!(out, "globals:").unwrap();
+        }
+        for (name, value) in current.bindings() {
+            writeln!(out, "  {} = {}", name, value).unwrap();
+        }
+        environment = current.enclosing.clone();
+        depth += 1;
+    }
+    out
+}
+
+/// Lists the tokens scanned from `source`, one per line, along with any
+/// errors found while scanning.
+pub fn list_tokens(source: &str) -> (String, CompileErrors) {
+    let (tokens, errors) = Scanner::new(source).scan_tokens();
+    let out = tokens.iter().fold(String::new(), |mut out, token| {
+        match &token.literal {
+            Some(literal) => writeln!(out, "{} {} ({})", token.span, token, literal),
+            None => writeln!(out, "{} {}", token.span, token),
+        }
+        .unwrap();
+        out
+    });
+    let errors = CompileErrors {
+        scanner: errors,
+        ..Default::default()
+    };
+    (out, errors)
+}
+
+fn readline_error(err: ReadlineError) -> LoxError {
+    match err {
+        ReadlineError::Io(err) => LoxError::Io(err),
+        err => LoxError::Io(io::Error::other(err)),
+    }
+}
+
+/// Whether `source` ends part way through a construct which spans lines:
+/// inside a string or comment, or with unclosed brackets of any kind.
+fn is_incomplete(source: &str) -> bool {
+    let (tokens, errors) = Scanner::new(source).scan_tokens();
+    let unterminated = errors.iter().any(|err| {
+        matches!(
+            err.kind,
+            ScannerErrorKind::UnterminatedString | ScannerErrorKind::UnterminatedComment
+        )
+    });
+    let depth = tokens.iter().fold(0, |depth, token| match token.typ {
+        TokenType::LeftBrace | TokenType::LeftParen | TokenType::LeftBracket => depth + 1,
+        TokenType::RightBrace | TokenType::RightParen | TokenType::RightBracket => depth - 1,
+        _ => depth,
+    });
+    unterminated || depth > 0
+}
+
+/// Continues input onto another line while it is incomplete.
+struct LoxHelper;
+
+impl Validator for LoxHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        if is_incomplete(ctx.input()) {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
+impl Completer for LoxHelper {
+    type Candidate = String;
+}
+
+impl Hinter for LoxHelper {
+    type Hint = String;
+}
+
+impl Highlighter for LoxHelper {}
+
+impl Helper for LoxHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_incomplete() {
+        assert!(is_incomplete("fun f() {"));
+        assert!(is_incomplete("print (1 +"));
+        assert!(is_incomplete("print \"multi"));
+        assert!(is_incomplete("/* comment"));
+        assert!(!is_incomplete("fun f() {}"));
+        assert!(!is_incomplete("print 1 +"));
+        assert!(!is_incomplete("}"));
+    }
+
+    #[test]
+    fn test_list_environment() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .run_source("var b = 2; var a = \"one\";", "<stdin>")
+            .unwrap();
+        assert_eq!(
+            "globals:\n  a = one\n  args = []\n  b = 2\n  clock = <native fn clock>\n  \
+             env = <native fn env>\n  exit = <native fn exit>\n",
+            list_environment(&interpreter)
+        );
+    }
+
+    #[test]
+    fn test_list_tokens() {
+        let (tokens, errors) = list_tokens("print 1;");
+        assert_eq!(
+            "1:1 Print print\n1:7 Number 1 (1)\n1:8 Semicolon ;\n1:9 EOF \n",
+            tokens
+        );
+        assert!(errors.is_empty());
+        let (tokens, errors) = list_tokens("@");
+        assert_eq!("1:1 Error @\n1:2 EOF \n", tokens);
+        assert_eq!(1, errors.scanner.len());
+    }
+}
@@ -0,0 +1,410 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    expr::{self, Expr, ExprId, ExpressionVisitor},
+    interpreter::Interpreter,
+    parser::{CompileErrors, ParseError},
+    stmt::{FunctionDecl, Stmt, StmtVisitor},
+    token::Token,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FunctionType {
+    None,
+    Function,
+    Method,
+    Initializer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+/// Statically resolves every variable reference to the scope it is bound in,
+/// recording the number of scopes between the reference and its declaration
+/// with the interpreter.
+///
+/// Variables which cannot be found in any local scope are assumed to be
+/// globals and are left unresolved.
+///
+/// Along the way, the resolver reports semantic errors which can be detected
+/// before any code runs.
+pub struct Resolver<'a> {
+    interpreter: &'a mut Interpreter,
+    /// The stack of local scopes currently in effect. Each maps a variable
+    /// name to whether its initializer has finished resolving.
+    scopes: Vec<HashMap<String, bool>>,
+    current_function: FunctionType,
+    current_class: ClassType,
+    errors: Vec<ParseError>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(interpreter: &'a mut Interpreter) -> Self {
+        Self {
+            interpreter,
+            scopes: Vec::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Resolves a program, returning every error found.
+    pub fn resolve_program(mut self, statements: &[Stmt]) -> Result<(), CompileErrors> {
+        self.resolve(statements);
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(CompileErrors {
+                scanner: Vec::new(),
+                parser: self.errors,
+            })
+        }
+    }
+
+    fn resolve(&mut self, statements: &[Stmt]) {
+        for stmt in statements {
+            stmt.accept(self);
+        }
+    }
+
+    fn error(&mut self, token: &Token, message: impl Into<String>) {
+        self.errors.push(ParseError::new(token.clone(), message));
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) {
+        expr.accept(self);
+    }
+
+    fn resolve_function(&mut self, function: &FunctionDecl, typ: FunctionType) {
+        let enclosing_function = self.current_function;
+        self.current_function = typ;
+
+        self.begin_scope();
+        for param in &function.params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve(&function.body);
+        self.end_scope();
+
+        self.current_function = enclosing_function;
+    }
+
+    /// Records the distance to the innermost scope declaring `name`, if any.
+    fn resolve_local(&mut self, id: ExprId, name: &Token) {
+        if let Some(depth) = self
+            .scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(&name.lexeme))
+        {
+            self.interpreter.resolve(id, depth);
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+        if scope.contains_key(&name.lexeme) {
+            self.error(
+                name,
+                format!("Already a variable named '{}' in this scope.", name.lexeme),
+            );
+        } else {
+            scope.insert(name.lexeme.clone(), false);
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), true);
+        }
+    }
+}
+
+impl ExpressionVisitor for Resolver<'_> {
+    type Output = ();
+
+    fn visit_binary(&mut self, expr: &expr::BinaryExpr) -> Self::Output {
+        self.resolve_expr(&expr.left);
+        self.resolve_expr(&expr.right);
+    }
+
+    fn visit_grouping(&mut self, expr: &expr::GroupingExpr) -> Self::Output {
+        self.resolve_expr(&expr.expression);
+    }
+
+    fn visit_literal(&mut self, _expr: &expr::LiteralExpr) -> Self::Output {}
+
+    fn visit_unary(&mut self, expr: &expr::UnaryExpr) -> Self::Output {
+        self.resolve_expr(&expr.right);
+    }
+
+    fn visit_variable(&mut self, expr: &expr::VariableExpr) -> Self::Output {
+        let scope = self.scopes.last();
+        if scope.and_then(|scope| scope.get(&expr.name.lexeme)) == Some(&false) {
+            self.error(
+                &expr.name,
+                format!(
+                    "Can't read local variable '{}' in its own initializer.",
+                    expr.name.lexeme
+                ),
+            );
+        }
+        self.resolve_local(expr.id, &expr.name);
+    }
+
+    fn visit_assign(&mut self, expr: &expr::AssignExpr) -> Self::Output {
+        self.resolve_expr(&expr.value);
+        self.resolve_local(expr.id, &expr.name);
+    }
+
+    fn visit_logical(&mut self, expr: &expr::LogicalExpr) -> Self::Output {
+        self.resolve_expr(&expr.left);
+        self.resolve_expr(&expr.right);
+    }
+
+    fn visit_call(&mut self, expr: &expr::CallExpr) -> Self::Output {
+        self.resolve_expr(&expr.callee);
+        for argument in &expr.arguments {
+            self.resolve_expr(argument);
+        }
+    }
+
+    fn visit_get(&mut self, expr: &expr::GetExpr) -> Self::Output {
+        self.resolve_expr(&expr.object);
+    }
+
+    fn visit_set(&mut self, expr: &expr::SetExpr) -> Self::Output {
+        self.resolve_expr(&expr.value);
+        self.resolve_expr(&expr.object);
+    }
+
+    fn visit_index(&mut self, expr: &expr::IndexExpr) -> Self::Output {
+        self.resolve_expr(&expr.object);
+        self.resolve_expr(&expr.index);
+    }
+
+    fn visit_this(&mut self, expr: &expr::ThisExpr) -> Self::Output {
+        if self.current_class == ClassType::None {
+            self.error(&expr.keyword, "Can't use 'this' outside of a class.");
+            return;
+        }
+        self.resolve_local(expr.id, &expr.keyword);
+    }
+
+    fn visit_super(&mut self, expr: &expr::SuperExpr) -> Self::Output {
+        match self.current_class {
+            ClassType::None => {
+                self.error(&expr.keyword, "Can't use 'super' outside of a class.");
+            }
+            ClassType::Class => {
+                self.error(
+                    &expr.keyword,
+                    "Can't use 'super' in a class with no superclass.",
+                );
+            }
+            ClassType::Subclass => self.resolve_local(expr.id, &expr.keyword),
+        }
+    }
+}
+
+impl StmtVisitor for Resolver<'_> {
+    type Output = ();
+
+    fn visit_expression(&mut self, expr: &Expr) -> Self::Output {
+        self.resolve_expr(expr);
+    }
+
+    fn visit_print(&mut self, expr: &Expr) -> Self::Output {
+        self.resolve_expr(expr);
+    }
+
+    fn visit_var(&mut self, name: &Token, initializer: &Option<Expr>) -> Self::Output {
+        self.declare(name);
+        if let Some(initializer) = initializer {
+            self.resolve_expr(initializer);
+        }
+        self.define(name);
+    }
+
+    fn visit_block(&mut self, statements: &[Stmt]) -> Self::Output {
+        self.begin_scope();
+        self.resolve(statements);
+        self.end_scope();
+    }
+
+    fn visit_if(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> Self::Output {
+        self.resolve_expr(condition);
+        then_branch.accept(self);
+        if let Some(else_branch) = else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_while(
+        &mut self,
+        condition: &Expr,
+        body: &Stmt,
+        increment: Option<&Expr>,
+        _label: Option<&Token>,
+    ) -> Self::Output {
+        self.resolve_expr(condition);
+        body.accept(self);
+        if let Some(increment) = increment {
+            self.resolve_expr(increment);
+        }
+    }
+
+    fn visit_function(&mut self, declaration: &Rc<FunctionDecl>) -> Self::Output {
+        // Define the name eagerly so the function can refer to itself.
+        self.declare(&declaration.name);
+        self.define(&declaration.name);
+        self.resolve_function(declaration, FunctionType::Function);
+    }
+
+    fn visit_return(&mut self, keyword: &Token, value: &Option<Expr>) -> Self::Output {
+        if self.current_function == FunctionType::None {
+            self.error(keyword, "Can't return from top-level code.");
+        }
+        if let Some(value) = value {
+            if self.current_function == FunctionType::Initializer {
+                self.error(keyword, "Can't return a value from an initializer.");
+            }
+            self.resolve_expr(value);
+        }
+    }
+
+    fn visit_break(&mut self, _keyword: &Token, _label: Option<&Token>) -> Self::Output {}
+
+    fn visit_continue(&mut self, _keyword: &Token, _label: Option<&Token>) -> Self::Output {}
+
+    fn visit_class(
+        &mut self,
+        name: &Token,
+        superclass: Option<&expr::VariableExpr>,
+        methods: &[Rc<FunctionDecl>],
+    ) -> Self::Output {
+        let enclosing_class = self.current_class;
+        self.current_class = ClassType::Class;
+
+        self.declare(name);
+        self.define(name);
+
+        if let Some(superclass) = superclass {
+            self.current_class = ClassType::Subclass;
+            self.visit_variable(superclass);
+            self.begin_scope();
+            self.scopes
+                .last_mut()
+                .unwrap()
+                .insert(String::from("super"), true);
+        }
+
+        self.begin_scope();
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(String::from("this"), true);
+        for method in methods {
+            let typ = if method.name.lexeme == "init" {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+            self.resolve_function(method, typ);
+        }
+        self.end_scope();
+
+        if superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.current_class = enclosing_class;
+    }
+
+    fn visit_throw(&mut self, _keyword: &Token, value: &Expr) -> Self::Output {
+        self.resolve_expr(value);
+    }
+
+    fn visit_try(
+        &mut self,
+        body: &[Stmt],
+        catch: Option<&(Token, Vec<Stmt>)>,
+        finally: Option<&[Stmt]>,
+    ) -> Self::Output {
+        self.visit_block(body);
+        if let Some((name, body)) = catch {
+            // The caught value shares a scope with the catch body.
+            self.begin_scope();
+            self.declare(name);
+            self.define(name);
+            self.resolve(body);
+            self.end_scope();
+        }
+        if let Some(finally) = finally {
+            self.visit_block(finally);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+
+    fn resolve(source: &str) -> Vec<String> {
+        let (tokens, errors) = Scanner::new(source).scan_tokens();
+        let stmts = Parser::new(tokens, errors).parse().unwrap();
+        let mut interpreter = Interpreter::new();
+        match Resolver::new(&mut interpreter).resolve_program(&stmts) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.parser.into_iter().map(|err| err.message).collect(),
+        }
+    }
+
+    #[test]
+    fn test_static_errors() {
+        assert_eq!(
+            vec!["Can't read local variable 'a' in its own initializer."],
+            resolve("var a = 1; { var a = a; }")
+        );
+        assert_eq!(
+            vec!["Already a variable named 'a' in this scope."],
+            resolve("fun f() { var a = 1; var a = 2; }")
+        );
+        assert_eq!(
+            vec![
+                "Can't return from top-level code.",
+                "Can't use 'this' outside of a class.",
+                "Can't use 'super' in a class with no superclass.",
+                "Can't return a value from an initializer.",
+            ],
+            resolve("return 1; print this; class A { m() { super.m(); } init() { return 1; } }")
+        );
+    }
+
+    #[test]
+    fn test_globals_may_be_redeclared() {
+        assert!(resolve("var a = 1; var a = a;").is_empty());
+    }
+}
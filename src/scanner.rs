@@ -1,5 +1,4 @@
 use crate::token::*;
-use crate::Result;
 use std::collections::HashMap;
 use std::error;
 use std::fmt;
@@ -7,19 +6,32 @@ use std::ops::Range;
 
 pub struct Scanner {
     source: Vec<char>,
+    /// The identity given to the spans of scanned tokens.
+    source_id: SourceId,
+    /// The byte offset of each character in `source`, plus the total length.
+    offsets: Vec<usize>,
     tokens: Vec<Token>,
+    errors: Vec<ScannerError>,
     start: usize,
     current: usize,
     line: usize,
+    /// The index of the first character of the current line.
+    line_start: usize,
+    /// The line and column at which the current token starts.
+    start_position: (usize, usize),
 }
 
 lazy_static! {
     static ref RESERVED_WORDS: HashMap<&'static str, TokenType> = {
         let mut m = HashMap::new();
         m.insert("and", TokenType::And);
+        m.insert("break", TokenType::Break);
+        m.insert("catch", TokenType::Catch);
         m.insert("class", TokenType::Class);
+        m.insert("continue", TokenType::Continue);
         m.insert("else", TokenType::Else);
         m.insert("false", TokenType::False);
+        m.insert("finally", TokenType::Finally);
         m.insert("for", TokenType::For);
         m.insert("fun", TokenType::Fun);
         m.insert("if", TokenType::If);
@@ -29,7 +41,9 @@ lazy_static! {
         m.insert("return", TokenType::Return);
         m.insert("super", TokenType::Super);
         m.insert("this", TokenType::This);
+        m.insert("throw", TokenType::Throw);
         m.insert("true", TokenType::True);
+        m.insert("try", TokenType::Try);
         m.insert("var", TokenType::Var);
         m.insert("while", TokenType::While);
         m
@@ -39,32 +53,61 @@ lazy_static! {
 impl Scanner {
     pub fn new(source: &str) -> Self {
         let chars = source.chars().collect();
+        let offsets = source
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain(std::iter::once(source.len()))
+            .collect();
         Scanner {
             source: chars,
+            source_id: 0,
+            offsets,
             tokens: Vec::new(),
+            errors: Vec::new(),
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            start_position: (1, 1),
         }
     }
 
-    pub fn scan_tokens(&mut self) -> Result<Vec<Token>> {
+    /// Sets the source identity recorded in the span of each token.
+    pub fn with_source_id(self, source_id: SourceId) -> Self {
+        Scanner { source_id, ..self }
+    }
+
+    /// Scans the whole source, returning its tokens along with any lexical
+    /// errors. Each error also produces a [`TokenType::Error`] token in its
+    /// place, so that scanning can continue past it.
+    pub fn scan_tokens(&mut self) -> (Vec<Token>, Vec<ScannerError>) {
+        // Scripts may start with a `#!` line so they can be run directly.
+        if self.source.starts_with(&['#', '!']) {
+            while self.peek() != Some('\n') && !self.is_at_end() {
+                self.advance();
+            }
+        }
+
         while !self.is_at_end() {
-            self.start = self.current;
-            self.scan_token()?;
+            self.begin_token();
+            self.scan_token();
         }
 
+        self.begin_token();
         self.tokens
-            .push(Token::new(TokenType::EOF, String::new(), None, self.line));
-        Ok(self.tokens.clone())
+            .push(Token::new(TokenType::EOF, String::new(), None, self.span()));
+        (self.tokens.clone(), self.errors.clone())
     }
 
-    fn scan_token(&mut self) -> Result<()> {
+    fn scan_token(&mut self) {
         match self.advance() {
             '(' => self.add_token(TokenType::LeftParen),
             ')' => self.add_token(TokenType::RightParen),
             '{' => self.add_token(TokenType::LeftBrace),
             '}' => self.add_token(TokenType::RightBrace),
+            '[' => self.add_token(TokenType::LeftBracket),
+            ']' => self.add_token(TokenType::RightBracket),
+            ':' => self.add_token(TokenType::Colon),
             ',' => self.add_token(TokenType::Comma),
             '.' => self.add_token(TokenType::Dot),
             '-' => self.add_token(TokenType::Minus),
@@ -109,23 +152,25 @@ impl Scanner {
                         self.advance();
                     }
                 } else if self.matches('*') {
-                    self.multiline_comment()?;
+                    self.multiline_comment();
                 } else {
                     self.add_token(TokenType::Slash);
                 }
             }
             ' ' | '\r' | '\t' => {}
-            '\n' => self.line += 1,
-            '"' => self.string()?,
-            '0'..='9' => self.number()?,
+            '\n' => self.newline(),
+            '"' => self.string(),
+            '0'..='9' => self.number(),
             'a'..='z' | 'A'..='Z' | '_' => self.identifier(),
-            _ => {
-                return Err(
-                    ScannerError::new(self.line, String::from("Unexpected character.")).into(),
-                )
-            }
+            _ => self.error(ScannerErrorKind::UnexpectedCharacter),
         }
-        Ok(())
+    }
+
+    /// Records an error covering the current token and emits an error token
+    /// in its place.
+    fn error(&mut self, kind: ScannerErrorKind) {
+        self.errors.push(ScannerError::new(self.span(), kind));
+        self.add_token(TokenType::Error);
     }
 
     fn add_token(&mut self, typ: TokenType) {
@@ -134,7 +179,35 @@ impl Scanner {
 
     fn add_token_literal(&mut self, typ: TokenType, literal: Option<Literal>) {
         let text = self.value_for(self.start..self.current);
-        self.tokens.push(Token::new(typ, text, literal, self.line));
+        self.tokens
+            .push(Token::new(typ, text, literal, self.span()));
+    }
+
+    /// Marks the current position as the start of a new token.
+    fn begin_token(&mut self) {
+        self.start = self.current;
+        self.start_position = (self.line, self.current - self.line_start + 1);
+    }
+
+    /// The span of the current token, from its first character up to the
+    /// current position.
+    fn span(&self) -> Span {
+        let (line, column) = self.start_position;
+        Span {
+            source: self.source_id,
+            ..Span::new(
+                self.offsets[self.start],
+                self.offsets[self.current],
+                line,
+                column,
+            )
+        }
+    }
+
+    /// Records that a newline character was just consumed.
+    fn newline(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
     }
 
     fn advance(&mut self) -> char {
@@ -172,26 +245,25 @@ impl Scanner {
         self.current >= self.source.len()
     }
 
-    fn string(&mut self) -> Result<()> {
+    fn string(&mut self) {
         while self.peek() != Some('"') && !self.is_at_end() {
             if self.advance() == '\n' {
-                self.line += 1;
+                self.newline();
             }
         }
 
         if self.is_at_end() {
-            return Err(ScannerError::new(self.line, String::from("Unterminated string.")).into());
+            self.error(ScannerErrorKind::UnterminatedString);
+            return;
         }
 
         self.advance();
 
         let value = self.value_for(self.start + 1..self.current - 1);
         self.add_token_literal(TokenType::String, Some(Literal::String(value)));
-
-        Ok(())
     }
 
-    fn number(&mut self) -> Result<()> {
+    fn number(&mut self) {
         while let Some('0'..='9') = self.peek() {
             self.advance();
         }
@@ -209,9 +281,8 @@ impl Scanner {
         }
 
         let value = self.value_for(self.start..self.current);
-        self.add_token_literal(TokenType::Number, Some(Literal::Number(value.parse()?)));
-
-        Ok(())
+        let value = value.parse().expect("digits form a valid number");
+        self.add_token_literal(TokenType::Number, Some(Literal::Number(value)));
     }
 
     fn identifier(&mut self) {
@@ -239,19 +310,18 @@ impl Scanner {
         }
     }
 
-    fn multiline_comment(&mut self) -> Result<()> {
+    fn multiline_comment(&mut self) {
         // Search for matching "*/"
         loop {
             while self.peek() != Some('*') && !self.is_at_end() {
                 if self.advance() == '\n' {
-                    self.line += 1;
+                    self.newline();
                 }
             }
 
             if self.is_at_end() {
-                return Err(
-                    ScannerError::new(self.line, String::from("Unterminated comment.")).into(),
-                );
+                self.error(ScannerErrorKind::UnterminatedComment);
+                return;
             }
 
             // Consume '*'
@@ -261,7 +331,6 @@ impl Scanner {
                 break;
             }
         }
-        Ok(())
     }
 
     fn value_for(&self, index: Range<usize>) -> String {
@@ -269,15 +338,33 @@ impl Scanner {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScannerErrorKind {
+    UnexpectedCharacter,
+    UnterminatedString,
+    UnterminatedComment,
+}
+
+impl fmt::Display for ScannerErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            ScannerErrorKind::UnexpectedCharacter => "Unexpected character.",
+            ScannerErrorKind::UnterminatedString => "Unterminated string.",
+            ScannerErrorKind::UnterminatedComment => "Unterminated comment.",
+        };
+        write!(f, "{}", description)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ScannerError {
-    line: usize,
-    description: String,
+    pub span: Span,
+    pub kind: ScannerErrorKind,
 }
 
 impl ScannerError {
-    pub fn new(line: usize, description: String) -> Self {
-        ScannerError { line, description }
+    pub fn new(span: Span, kind: ScannerErrorKind) -> Self {
+        ScannerError { span, kind }
     }
 }
 
@@ -285,6 +372,63 @@ impl error::Error for ScannerError {}
 
 impl fmt::Display for ScannerError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "[line {}] Error: {}", self.line, self.description)
+        write!(f, "[line {}] Error: {}", self.span, self.kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_spans() {
+        let (tokens, _) = Scanner::new("print \"héllo\";\n  x").scan_tokens();
+        let spans: Vec<_> = tokens.iter().map(|token| token.span).collect();
+        assert_eq!(
+            vec![
+                Span::new(0, 5, 1, 1),
+                Span::new(6, 14, 1, 7),
+                Span::new(14, 15, 1, 14),
+                Span::new(18, 19, 2, 3),
+                Span::new(19, 19, 2, 4),
+            ],
+            spans
+        );
+    }
+
+    #[test]
+    fn test_continues_after_errors() {
+        let (tokens, errors) = Scanner::new("@ 1 # \"open").scan_tokens();
+        let types: Vec<_> = tokens.iter().map(|token| token.typ).collect();
+        assert_eq!(
+            vec![
+                TokenType::Error,
+                TokenType::Number,
+                TokenType::Error,
+                TokenType::Error,
+                TokenType::EOF,
+            ],
+            types
+        );
+        let errors: Vec<_> = errors.iter().map(|err| err.to_string()).collect();
+        assert_eq!(
+            vec![
+                "[line 1:1] Error: Unexpected character.",
+                "[line 1:5] Error: Unexpected character.",
+                "[line 1:7] Error: Unterminated string.",
+            ],
+            errors
+        );
+    }
+
+    #[test]
+    fn test_skips_shebang() {
+        let (tokens, errors) = Scanner::new("#!/usr/bin/env rlox\nprint 1;").scan_tokens();
+        assert!(errors.is_empty());
+        assert_eq!(TokenType::Print, tokens[0].typ);
+        assert_eq!(Span::new(20, 25, 2, 1), tokens[0].span);
+        // Only on the first line.
+        let (_, errors) = Scanner::new("print 1;\n#!/usr/bin/env rlox").scan_tokens();
+        assert!(!errors.is_empty());
     }
 }
@@ -1,3 +1,5 @@
+use std::{fmt, rc::Rc};
+
 use crate::{expr, token::Token};
 
 #[derive(Debug, Clone)]
@@ -6,16 +8,40 @@ pub enum Stmt {
     Print(expr::Expr),
     Var(Token, Option<expr::Expr>),
     Block(Vec<Stmt>),
+    If(expr::Expr, Box<Stmt>, Option<Box<Stmt>>),
+    /// A loop, with the increment clause of a `for` loop which runs after
+    /// each iteration (including on `continue`), and an optional label.
+    While(
+        expr::Expr,
+        Box<Stmt>,
+        Option<Box<expr::Expr>>,
+        Option<Token>,
+    ),
+    Function(Rc<FunctionDecl>),
+    Return(Token, Option<expr::Expr>),
+    /// `break`, with the label of the loop to break out of, if any.
+    Break(Token, Option<Token>),
+    /// `continue`, with the label of the loop to continue, if any.
+    Continue(Token, Option<Token>),
+    Class(Token, Option<expr::VariableExpr>, Vec<Rc<FunctionDecl>>),
+    Throw(Token, expr::Expr),
+    /// A `try` block, followed by an optional `catch` clause binding the
+    /// caught value to a name, and an optional `finally` block.
+    Try(Vec<Stmt>, Option<(Token, Vec<Stmt>)>, Option<Vec<Stmt>>),
+}
+
+/// A function declaration. Shared via [`Rc`] so that runtime function values
+/// can hold onto their declaration without cloning the body.
+#[derive(Debug, Clone)]
+pub struct FunctionDecl {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Vec<Stmt>,
 }
 
 impl Stmt {
     pub fn accept<Visitor: StmtVisitor>(&self, visitor: &mut Visitor) -> Visitor::Output {
-        match self {
-            Stmt::Expression(expr) => visitor.visit_expression(expr),
-            Stmt::Print(expr) => visitor.visit_print(expr),
-            Stmt::Var(name, initializer) => visitor.visit_var(name, initializer),
-            Stmt::Block(statements) => visitor.visit_block(statements),
-        }
+        visitor.visit(self)
     }
 }
 
@@ -28,6 +54,23 @@ pub trait StmtVisitor {
             Stmt::Print(stmt) => self.visit_print(stmt),
             Stmt::Var(name, initializer) => self.visit_var(name, initializer),
             Stmt::Block(statements) => self.visit_block(statements),
+            Stmt::If(condition, then_branch, else_branch) => {
+                self.visit_if(condition, then_branch, else_branch.as_deref())
+            }
+            Stmt::While(condition, body, increment, label) => {
+                self.visit_while(condition, body, increment.as_deref(), label.as_ref())
+            }
+            Stmt::Function(declaration) => self.visit_function(declaration),
+            Stmt::Return(keyword, value) => self.visit_return(keyword, value),
+            Stmt::Break(keyword, label) => self.visit_break(keyword, label.as_ref()),
+            Stmt::Continue(keyword, label) => self.visit_continue(keyword, label.as_ref()),
+            Stmt::Class(name, superclass, methods) => {
+                self.visit_class(name, superclass.as_ref(), methods)
+            }
+            Stmt::Throw(keyword, value) => self.visit_throw(keyword, value),
+            Stmt::Try(body, catch, finally) => {
+                self.visit_try(body, catch.as_ref(), finally.as_deref())
+            }
         }
     }
 
@@ -35,4 +78,227 @@ pub trait StmtVisitor {
     fn visit_print(&mut self, stmt: &expr::Expr) -> Self::Output;
     fn visit_var(&mut self, name: &Token, initializer: &Option<expr::Expr>) -> Self::Output;
     fn visit_block(&mut self, statements: &[Stmt]) -> Self::Output;
+    fn visit_if(
+        &mut self,
+        condition: &expr::Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> Self::Output;
+    fn visit_while(
+        &mut self,
+        condition: &expr::Expr,
+        body: &Stmt,
+        increment: Option<&expr::Expr>,
+        label: Option<&Token>,
+    ) -> Self::Output;
+    fn visit_function(&mut self, declaration: &Rc<FunctionDecl>) -> Self::Output;
+    fn visit_return(&mut self, keyword: &Token, value: &Option<expr::Expr>) -> Self::Output;
+    fn visit_break(&mut self, keyword: &Token, label: Option<&Token>) -> Self::Output;
+    fn visit_continue(&mut self, keyword: &Token, label: Option<&Token>) -> Self::Output;
+    fn visit_class(
+        &mut self,
+        name: &Token,
+        superclass: Option<&expr::VariableExpr>,
+        methods: &[Rc<FunctionDecl>],
+    ) -> Self::Output;
+    fn visit_throw(&mut self, keyword: &Token, value: &expr::Expr) -> Self::Output;
+    fn visit_try(
+        &mut self,
+        body: &[Stmt],
+        catch: Option<&(Token, Vec<Stmt>)>,
+        finally: Option<&[Stmt]>,
+    ) -> Self::Output;
+}
+
+/// Prints statements as S-expressions, in the style of the expression printer.
+struct Printer;
+
+impl Printer {
+    /// Prints `(name parts...)`, skipping any empty parts.
+    fn parenthesize(&mut self, name: &str, parts: Vec<String>) -> String {
+        let mut builder = format!("({}", name);
+        for part in parts.iter().filter(|part| !part.is_empty()) {
+            builder += " ";
+            builder += part;
+        }
+        builder += ")";
+        builder
+    }
+
+    fn block(&mut self, name: &str, statements: &[Stmt]) -> String {
+        let parts = statements.iter().map(|stmt| stmt.accept(self)).collect();
+        self.parenthesize(name, parts)
+    }
+}
+
+impl StmtVisitor for Printer {
+    type Output = String;
+
+    fn visit_expression(&mut self, stmt: &expr::Expr) -> Self::Output {
+        self.parenthesize(";", vec![stmt.to_string()])
+    }
+
+    fn visit_print(&mut self, stmt: &expr::Expr) -> Self::Output {
+        self.parenthesize("print", vec![stmt.to_string()])
+    }
+
+    fn visit_var(&mut self, name: &Token, initializer: &Option<expr::Expr>) -> Self::Output {
+        match initializer {
+            Some(initializer) => self.parenthesize(
+                "var",
+                vec![
+                    name.lexeme.clone(),
+                    String::from("="),
+                    initializer.to_string(),
+                ],
+            ),
+            None => self.parenthesize("var", vec![name.lexeme.clone()]),
+        }
+    }
+
+    fn visit_block(&mut self, statements: &[Stmt]) -> Self::Output {
+        self.block("block", statements)
+    }
+
+    fn visit_if(
+        &mut self,
+        condition: &expr::Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> Self::Output {
+        match else_branch {
+            Some(else_branch) => {
+                let parts = vec![
+                    condition.to_string(),
+                    then_branch.accept(self),
+                    else_branch.accept(self),
+                ];
+                self.parenthesize("if-else", parts)
+            }
+            None => {
+                let parts = vec![condition.to_string(), then_branch.accept(self)];
+                self.parenthesize("if", parts)
+            }
+        }
+    }
+
+    fn visit_while(
+        &mut self,
+        condition: &expr::Expr,
+        body: &Stmt,
+        increment: Option<&expr::Expr>,
+        label: Option<&Token>,
+    ) -> Self::Output {
+        let parts = vec![
+            condition.to_string(),
+            body.accept(self),
+            increment
+                .map(|increment| increment.to_string())
+                .unwrap_or_default(),
+        ];
+        let name = match label {
+            Some(label) => format!("while:{}", label.lexeme),
+            None => String::from("while"),
+        };
+        self.parenthesize(&name, parts)
+    }
+
+    fn visit_function(&mut self, declaration: &Rc<FunctionDecl>) -> Self::Output {
+        let params: Vec<_> = declaration
+            .params
+            .iter()
+            .map(|param| param.lexeme.as_str())
+            .collect();
+        let mut parts = vec![
+            declaration.name.lexeme.clone(),
+            format!("({})", params.join(" ")),
+        ];
+        parts.extend(declaration.body.iter().map(|stmt| stmt.accept(self)));
+        self.parenthesize("fun", parts)
+    }
+
+    fn visit_return(&mut self, _keyword: &Token, value: &Option<expr::Expr>) -> Self::Output {
+        let value = value.as_ref().map(|value| value.to_string());
+        self.parenthesize("return", value.into_iter().collect())
+    }
+
+    fn visit_break(&mut self, _keyword: &Token, label: Option<&Token>) -> Self::Output {
+        let label = label.map(|label| label.lexeme.clone());
+        self.parenthesize("break", label.into_iter().collect())
+    }
+
+    fn visit_continue(&mut self, _keyword: &Token, label: Option<&Token>) -> Self::Output {
+        let label = label.map(|label| label.lexeme.clone());
+        self.parenthesize("continue", label.into_iter().collect())
+    }
+
+    fn visit_class(
+        &mut self,
+        name: &Token,
+        superclass: Option<&expr::VariableExpr>,
+        methods: &[Rc<FunctionDecl>],
+    ) -> Self::Output {
+        let mut parts = vec![name.lexeme.clone()];
+        if let Some(superclass) = superclass {
+            parts.push(format!("< {}", superclass.name.lexeme));
+        }
+        parts.extend(methods.iter().map(|method| self.visit_function(method)));
+        self.parenthesize("class", parts)
+    }
+
+    fn visit_throw(&mut self, _keyword: &Token, value: &expr::Expr) -> Self::Output {
+        self.parenthesize("throw", vec![value.to_string()])
+    }
+
+    fn visit_try(
+        &mut self,
+        body: &[Stmt],
+        catch: Option<&(Token, Vec<Stmt>)>,
+        finally: Option<&[Stmt]>,
+    ) -> Self::Output {
+        let mut parts = vec![self.block("block", body)];
+        if let Some((name, body)) = catch {
+            parts.push(self.block(&format!("catch {}", name.lexeme), body));
+        }
+        if let Some(finally) = finally {
+            parts.push(self.block("finally", finally));
+        }
+        self.parenthesize("try", parts)
+    }
+}
+
+impl fmt::Display for Stmt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.accept(&mut Printer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parser::Parser, scanner::Scanner};
+
+    #[test]
+    fn test_printer() {
+        let source = "
+            var a = 1;
+            fun f(x, y) { return x + y; }
+            outer: for (var i = 0; i < 3; i = i + 1) { if (i == a) break outer; else continue; }
+            class B < A { m() { print this.x; } }
+            try { throw \"e\"; } catch (e) { print e; } finally { a = nil; }
+        ";
+        let (tokens, errors) = Scanner::new(source).scan_tokens();
+        let stmts = Parser::new(tokens, errors).parse().unwrap();
+        let printed: Vec<_> = stmts.iter().map(|stmt| stmt.to_string()).collect();
+        assert_eq!(
+            vec![
+                "(var a = 1)",
+                "(fun f (x y) (return (+ x y)))",
+                "(block (var i = 0) (while:outer (< i 3) \
+                 (block (if-else (== i a) (break outer) (continue))) (i (+ i 1))))",
+                "(class B < A (fun m () (print (. this x))))",
+                "(try (block (throw e)) (catch e (print e)) (finally (; (a nil))))",
+            ],
+            printed
+        );
+    }
 }
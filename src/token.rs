@@ -9,6 +9,9 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Comma,
     Dot,
     Minus,
@@ -34,9 +37,13 @@ pub enum TokenType {
 
     // Keywords
     And,
+    Break,
+    Catch,
     Class,
+    Continue,
     Else,
     False,
+    Finally,
     Fun,
     For,
     If,
@@ -46,28 +53,81 @@ pub enum TokenType {
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
 
+    /// Stands in for source text which could not be scanned.
+    Error,
     EOF,
 }
 
+/// Identifies one of the source texts compiled by an interpreter.
+pub type SourceId = usize;
+
+/// A region of the source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    /// The source text the span is in.
+    pub source: SourceId,
+    /// The byte offset of the start of the span.
+    pub start: usize,
+    /// The byte offset just past the end of the span.
+    pub end: usize,
+    /// The 1-based line on which the span starts.
+    pub line: usize,
+    /// The 1-based column, in characters, at which the span starts.
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+        Span {
+            source: 0,
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+
+    /// Returns the smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        let (first, last) = if self.start <= other.start {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        Span {
+            end: first.end.max(last.end),
+            ..first
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub typ: TokenType,
     pub lexeme: String,
     pub literal: Option<Literal>,
-    pub line: usize,
+    pub span: Span,
 }
 
 impl Token {
-    pub fn new(typ: TokenType, lexeme: String, literal: Option<Literal>, line: usize) -> Self {
+    pub fn new(typ: TokenType, lexeme: String, literal: Option<Literal>, span: Span) -> Self {
         Token {
             typ,
             lexeme,
             literal,
-            line,
+            span,
         }
     }
 }
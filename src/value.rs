@@ -0,0 +1,123 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::{
+    callable::Callable,
+    class::{LoxClass, LoxInstance},
+    interpreter::{ErrorKind, RuntimeError},
+    token::{Literal, Token},
+};
+
+/// A runtime value produced by evaluating an expression.
+///
+/// Unlike [`Literal`], which only represents constants appearing in source
+/// code, values may refer to heap-allocated objects such as functions. These
+/// are reference-counted so that copying a value is always cheap.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Nil,
+    Boolean(bool),
+    Number(f64),
+    String(Rc<str>),
+    Callable(Rc<dyn Callable>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+    Error(Rc<ErrorValue>),
+    /// An immutable list, such as the script's arguments.
+    List(Rc<Vec<Value>>),
+}
+
+impl Value {
+    /// The name of this value's type, as shown in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Boolean(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Callable(_) => "function",
+            Value::Class(_) => "class",
+            Value::Instance(_) => "instance",
+            Value::Error(_) => "error",
+            Value::List(_) => "list",
+        }
+    }
+}
+
+impl From<Literal> for Value {
+    fn from(literal: Literal) -> Self {
+        match literal {
+            Literal::String(s) => Value::String(s.into()),
+            Literal::Number(n) => Value::Number(n),
+            Literal::Boolean(b) => Value::Boolean(b),
+            Literal::Nil => Value::Nil,
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            // Heap objects are only equal to themselves.
+            (Value::Callable(a), Value::Callable(b)) => Rc::ptr_eq(a, b),
+            (Value::Class(a), Value::Class(b)) => Rc::ptr_eq(a, b),
+            (Value::Instance(a), Value::Instance(b)) => Rc::ptr_eq(a, b),
+            (Value::Error(a), Value::Error(b)) => Rc::ptr_eq(a, b),
+            (Value::List(a), Value::List(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Callable(c) => write!(f, "{}", c),
+            Value::Class(c) => write!(f, "{}", c),
+            Value::Instance(i) => write!(f, "{}", i.borrow()),
+            Value::Error(e) => write!(f, "{}", e),
+            Value::List(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+/// A built-in runtime error which has been caught by a `catch` clause.
+#[derive(Debug)]
+pub struct ErrorValue {
+    pub message: String,
+    /// The line the error was raised on.
+    pub line: usize,
+}
+
+impl ErrorValue {
+    /// Looks up one of the error's properties: `message` or `line`.
+    pub fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        match name.lexeme.as_str() {
+            "message" => Ok(Value::String(self.message.as_str().into())),
+            "line" => Ok(Value::Number(self.line as f64)),
+            _ => Err(ErrorKind::UndefinedProperty(name.clone()).into()),
+        }
+    }
+}
+
+impl fmt::Display for ErrorValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<error: {}>", self.message)
+    }
+}